@@ -0,0 +1,55 @@
+//! # Debugging Utilities
+//!
+//! Helpers for inspecting data over a serial console, such as the UART.
+
+/// Number of bytes formatted on each line of a [`hexdump`].
+pub const HEXDUMP_BYTES_PER_LINE: usize = 16;
+
+// Offset (8) + gap (2) + hex bytes (16 * 3 + 1) + gap (1) + ASCII (16 + 2)
+// + line ending (2)
+const HEXDUMP_LINE_LEN: usize = 80;
+const HEXDUMP_HEX_START: usize = 10;
+const HEXDUMP_ASCII_START: usize = 61;
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Writes a hex and ASCII dump of `data`, with [`HEXDUMP_BYTES_PER_LINE`]
+/// bytes per line. Each line holds the offset of its first byte, the bytes
+/// in hex, and the bytes as ASCII (non-printable bytes are shown as `.`).
+/// Lines end with `\r\n`.
+///
+/// Each line is formatted into a buffer on the stack before being written,
+/// so no allocation is needed.
+///
+/// Example:
+/// ```
+/// hal::debug::hexdump(&mut uart, b"Hello, world!\r\n").unwrap();
+/// // Writes:
+/// // 00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0d 0a     |Hello, world!..|
+/// ```
+pub fn hexdump<W: embedded_io::Write>(writer: &mut W, data: &[u8]) -> Result<(), W::Error> {
+    for (line_idx, chunk) in data.chunks(HEXDUMP_BYTES_PER_LINE).enumerate() {
+        let mut line = [b' '; HEXDUMP_LINE_LEN];
+        // Offset of the first byte of the line
+        let offset = (line_idx * HEXDUMP_BYTES_PER_LINE) as u32;
+        for (i, c) in line[..8].iter_mut().enumerate() {
+            *c = HEX_DIGITS[((offset >> (28 - i * 4)) & 0xF) as usize];
+        }
+        // Hex and ASCII columns, with an extra gap after the eighth byte
+        for (i, byte) in chunk.iter().enumerate() {
+            let pos = HEXDUMP_HEX_START + i * 3 + i / 8;
+            line[pos] = HEX_DIGITS[(byte >> 4) as usize];
+            line[pos + 1] = HEX_DIGITS[(byte & 0xF) as usize];
+            line[HEXDUMP_ASCII_START + i] = match byte {
+                0x20..=0x7E => *byte,
+                _ => b'.',
+            };
+        }
+        let end = HEXDUMP_ASCII_START + chunk.len();
+        line[HEXDUMP_ASCII_START - 1] = b'|';
+        line[end] = b'|';
+        line[end + 1] = b'\r';
+        line[end + 2] = b'\n';
+        writer.write_all(&line[..end + 3])?;
+    }
+    Ok(())
+}
@@ -1,5 +1,33 @@
 //! # Flash Controller (FLC)
+//!
+//! ## Running code from RAM
+//!
+//! The MAX78000 has a single flash bank, which cannot be read while a write
+//! or erase is in progress. Code that runs during the operation must execute
+//! from SRAM, otherwise the instruction fetch fails with a hard fault.
+//!
+//! With the `flashprog-linkage` feature, the functions of [`Flc`] that wait
+//! for an operation to complete are placed in the `.flashprog` section, and
+//! [`ram_func!`](crate::ram_func) places your own functions there as well.
+//! The section must be added to the linker script, e.g. as `flashprog.x`
+//! next to `memory.x`, passed with `-C link-arg=-Tflashprog.x` after
+//! `-Tlink.x`:
+//! ```text
+//! SECTIONS {
+//!   .flashprog : ALIGN(4) {
+//!     __sflashprog = .;
+//!     *(.flashprog .flashprog.*);
+//!     . = ALIGN(4);
+//!     __eflashprog = .;
+//!   } > RAM AT > FLASH
+//!   __siflashprog = LOADADDR(.flashprog);
+//! } INSERT AFTER .data;
+//! ```
+//! Then call [`init_flashprog`] at the start of `main`, before the first
+//! write or erase, to copy the section from flash into RAM.
 use crate::gcr::clocks::{Clock, SystemClock};
+#[cfg(feature = "erase-counters")]
+use core::cell::Cell;
 
 /// Base address of the flash memory.
 pub const FLASH_BASE: u32 = 0x1000_0000;
@@ -12,8 +40,23 @@ pub const FLASH_PAGE_COUNT: u32 = 64;
 /// Size of a flash page.
 pub const FLASH_PAGE_SIZE: u32 = 0x2000;
 
+/// Base address of the information block.
+pub const INFO_BASE: u32 = 0x1080_0000;
+/// Size of the information block.
+pub const INFO_SIZE: u32 = 0x0000_4000;
+/// Length of the chip ID in bytes.
+pub const CHIP_ID_LEN: usize = 16;
+/// Minimum system clock frequency for flash write and erase operations. The
+/// flash controller clock divisor is the system clock frequency in MHz, so
+/// slower clocks (e.g. the 32.768 kHz ERTCO) would give a divisor of 0.
+pub const MIN_SYS_CLK_FREQUENCY: u32 = 1_000_000;
+/// Number of times the flash controller status is polled by [`safe_abort`]
+/// before giving up on an in-progress operation.
+pub const ABORT_TIMEOUT_POLLS: u32 = 1_000_000;
+
 /// Flash controller errors.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum FlashError {
     /// The target address or page to write or erase is invalid.
     InvalidAddress,
@@ -22,6 +65,8 @@ pub enum FlashError {
     /// Writing over the old data with new data would cause 0 -> 1 bit transitions.
     /// The target address must be erased before writing new data.
     NeedsErase,
+    /// The system clock is slower than [`MIN_SYS_CLK_FREQUENCY`].
+    ClockTooSlow,
 }
 
 /// # Flash Controller (FLC) Peripheral
@@ -55,16 +100,62 @@ pub enum FlashError {
 pub struct Flc {
     flc: crate::pac::Flc,
     sys_clk: Clock<SystemClock>,
+    #[cfg(feature = "erase-counters")]
+    erase_counts: [Cell<u32>; FLASH_PAGE_COUNT as usize],
 }
 
 impl Flc {
     /// Construct a new flash controller peripheral.
+    ///
+    /// The system clock must run at [`MIN_SYS_CLK_FREQUENCY`] (1 MHz) or
+    /// faster, otherwise writes and erases misbehave. Use [`Flc::try_new`] to
+    /// check this.
     pub fn new(flc: crate::pac::Flc, sys_clk: Clock<SystemClock>) -> Self {
-        let s = Self { flc, sys_clk };
+        let s = Self {
+            flc,
+            sys_clk,
+            #[cfg(feature = "erase-counters")]
+            erase_counts: core::array::from_fn(|_| Cell::new(0)),
+        };
         s.config();
         s
     }
 
+    /// Construct a new flash controller peripheral like [`Flc::new`], but
+    /// return [`FlashError::ClockTooSlow`] if the system clock is slower than
+    /// [`MIN_SYS_CLK_FREQUENCY`].
+    ///
+    /// Example:
+    /// ```
+    /// let flc = Flc::try_new(p.flc, clks.sys_clk).expect("system clock too slow for flash");
+    /// ```
+    pub fn try_new(flc: crate::pac::Flc, sys_clk: Clock<SystemClock>) -> Result<Self, FlashError> {
+        if sys_clk.frequency < MIN_SYS_CLK_FREQUENCY {
+            return Err(FlashError::ClockTooSlow);
+        }
+        Ok(Self::new(flc, sys_clk))
+    }
+
+    /// Wait for any write or erase to complete, lock the flash controller,
+    /// and return the FLC peripheral from the PAC. The flash controller has
+    /// no peripheral clock, since the flash is always needed to execute code.
+    pub fn release(self) -> crate::pac::Flc {
+        while self.is_busy() {}
+        self.lock_flash();
+        self.flc
+    }
+
+    /// Reconfigure the flash controller for a new system clock frequency.
+    ///
+    /// The flash controller clock divisor is derived from the system clock
+    /// frequency, so this must be called after the system clock changes and
+    /// before any further flash write or erase operations. Otherwise, the
+    /// divisor is stale and writes or erases may fail or corrupt data.
+    pub fn reconfigure(&mut self, sys_clk: &Clock<SystemClock>) {
+        self.sys_clk = *sys_clk;
+        self.config();
+    }
+
     /// Configure the flash controller.
     #[inline]
     fn config(&self) {
@@ -100,6 +191,15 @@ impl Flc {
         Ok(())
     }
 
+    /// Check if an offset is within the information block and 32-bit aligned.
+    #[inline]
+    pub fn check_info_offset(&self, offset: u32) -> Result<(), FlashError> {
+        if offset & 0b11 != 0 || offset >= INFO_SIZE {
+            return Err(FlashError::InvalidAddress);
+        }
+        Ok(())
+    }
+
     /// Check if an address is within the valid flash memory range.
     #[inline]
     pub fn check_page_number(&self, page_number: u32) -> Result<(), FlashError> {
@@ -122,13 +222,7 @@ impl Flc {
     /// Get the page number of a flash address.
     #[inline]
     pub fn get_page_number(&self, address: u32) -> Result<u32, FlashError> {
-        self.check_address(address)?;
-        let page_num = (address >> 13) & (FLASH_PAGE_COUNT - 1);
-        // Check for invalid page number (redundant check)
-        if page_num >= FLASH_PAGE_COUNT {
-            return Err(FlashError::InvalidAddress);
-        }
-        Ok(page_num)
+        page_number(address)
     }
 
     /// Set the target address for a write or erase operation.
@@ -190,12 +284,14 @@ impl Flc {
         // Ensure that the flash controller is configured
         self.config();
         // Verify that only 1 -> 0 transitions are being made by reading the existing data at the target address
-        for i in 0..4 {
+        let mut old_data = [0; 4];
+        for (i, word) in old_data.iter_mut().enumerate() {
             // Safety: We have checked the address already
-            let old_data = unsafe { core::ptr::read_volatile((address + i * 4) as *const u32) };
-            if (old_data & data[i as usize]) != data[i as usize] {
-                return Err(FlashError::NeedsErase);
-            }
+            *word = unsafe { core::ptr::read_volatile((address + i as u32 * 4) as *const u32) };
+        }
+        // Skip the write entirely if no bits would change
+        if !check_write(&old_data, data)? {
+            return Ok(());
         }
         self.set_address(address)?;
         // Safety: Data can be written to all bits of the data registers
@@ -240,7 +336,8 @@ impl Flc {
 
     /// Writes four [`u32`] to flash memory. Uses little-endian byte order.
     /// The lowest [`u32`] in the array is written to the lowest address in flash.
-    /// The target address must be 128-bit aligned.
+    /// The target address must be 128-bit aligned. If the target address
+    /// already holds `data`, nothing is written.
     ///
     /// Example:
     /// ```
@@ -253,13 +350,43 @@ impl Flc {
         self._write_128(address, &data)
     }
 
+    /// Writes four [`u32`] to flash memory like [`Flc::write_128`], retrying
+    /// up to `attempts` times in total if the write fails with
+    /// [`FlashError::AccessViolation`]. The flash controller is reconfigured
+    /// before each retry.
+    ///
+    /// [`FlashError::NeedsErase`] and [`FlashError::InvalidAddress`] are
+    /// returned immediately, since retrying cannot fix them. If every attempt
+    /// fails, the last [`FlashError::AccessViolation`] is returned. An
+    /// `attempts` of `0` is treated as `1`.
+    ///
+    /// Note: Retrying only smooths over transient contention. Flash accesses
+    /// from different contexts should still be serialized where possible.
+    pub fn write_128_retry(
+        &self,
+        address: u32,
+        data: &[u32; 4],
+        attempts: u8,
+    ) -> Result<(), FlashError> {
+        let mut result = self._write_128(address, data);
+        for _ in 1..attempts {
+            if result != Err(FlashError::AccessViolation) {
+                break;
+            }
+            self.config();
+            result = self._write_128(address, data);
+        }
+        result
+    }
+
     /// Write a [`u32`] to flash memory. Uses little-endian byte order.
     /// The target address must be 32-bit aligned.
     ///
     /// Note: Writes to flash memory must be done in 128-bit (16-byte) blocks.
     /// This function will read the existing 128-bit word containing the target
     /// address, modify the 32-bit word within the 128-bit word, and write the
-    /// modified 128-bit word back to flash memory.
+    /// modified 128-bit word back to flash memory. If the target address
+    /// already holds `data`, nothing is written.
     ///
     /// Example:
     /// ```
@@ -273,25 +400,52 @@ impl Flc {
         if address & 0b11 != 0 {
             return Err(FlashError::InvalidAddress);
         }
+        self._write_within_128(address, &[data])
+    }
+
+    /// Write consecutive [`u32`] within a 128-bit word to flash memory, by
+    /// reading the existing 128-bit word, merging `data` into it, and
+    /// writing it back. The caller must check the alignment of the address
+    /// for the length of `data`.
+    #[doc(hidden)]
+    #[inline]
+    fn _write_within_128(&self, address: u32, data: &[u32]) -> Result<(), FlashError> {
         self.check_address(address)?;
         let addr_128 = address & !0b1111;
-        self.check_address(addr_128)?;
-        let addr_128_ptr = addr_128 as *const u32;
         // Read existing data at the 128-bit word containing the target address
-        let mut prev_data: [u32; 4] = [0xFFFF_FFFF; 4];
-        // Safety: We have checked the address already
-        unsafe {
-            prev_data[0] = core::ptr::read_volatile(addr_128_ptr);
-            prev_data[1] = core::ptr::read_volatile(addr_128_ptr.offset(1));
-            prev_data[2] = core::ptr::read_volatile(addr_128_ptr.offset(2));
-            prev_data[3] = core::ptr::read_volatile(addr_128_ptr.offset(3));
+        let prev_data = self.read_128(addr_128)?;
+        // Determine index of the first 32-bit word within the 128-bit word
+        let data_idx = ((address & 0b1100) >> 2) as usize;
+        match merge_words(prev_data, data_idx, data) {
+            // Write the modified 128-bit word to flash memory
+            Some(new_data) => self._write_128(addr_128, &new_data),
+            // Nothing to write if the words already hold the data
+            None => Ok(()),
+        }
+    }
+
+    /// Write a [`u64`] to flash memory. Uses little-endian byte order.
+    /// The target address must be 64-bit aligned.
+    ///
+    /// Note: Like [`Flc::write_32`], this function will read the existing
+    /// 128-bit word containing the target address, modify the 64-bit half
+    /// within the 128-bit word, and write the modified 128-bit word back to
+    /// flash memory. If the target address already holds `data`, nothing is
+    /// written.
+    ///
+    /// Example:
+    /// ```
+    /// let data: u64 = 0xF0DE_BC9A_7856_3412;
+    /// flash.write_64(0x1006_0008, data).unwrap();
+    /// // The bytes in flash will look like:
+    /// // 10060000: FFFF FFFF FFFF FFFF 1234 5678 9ABC DEF0
+    /// ```
+    pub fn write_64(&self, address: u32, data: u64) -> Result<(), FlashError> {
+        // Target address must be 64-bit aligned
+        if address & 0b111 != 0 {
+            return Err(FlashError::InvalidAddress);
         }
-        // Determine index of the 32-bit word within the 128-bit word
-        let data_idx = (address & 0b1100) >> 2;
-        // Modify the 32-bit word within the 128-bit word
-        prev_data[data_idx as usize] = data;
-        // Write the modified 128-bit word to flash memory
-        self._write_128(addr_128, &prev_data)
+        self._write_within_128(address, &[data as u32, (data >> 32) as u32])
     }
 
     /// Reads four [`u32`] from flash memory. Uses little-endian byte order.
@@ -328,12 +482,89 @@ impl Flc {
         unsafe { Ok(core::ptr::read_volatile(addr_32_ptr)) }
     }
 
+    /// Reads 16 bytes from flash memory starting at any address. Unlike
+    /// [`Flc::read_128`], the address does not need to be aligned, so the
+    /// bytes may span two 128-bit words. All 16 bytes must be within the
+    /// valid flash memory range.
+    pub fn read_bytes_16(&self, address: u32) -> Result<[u8; 16], FlashError> {
+        self.check_address(address)?;
+        self.check_address(address.checked_add(15).ok_or(FlashError::InvalidAddress)?)?;
+        let mut data = [0; 16];
+        for (i, byte) in data.iter_mut().enumerate() {
+            // Safety: We have checked the address range already
+            *byte = unsafe { core::ptr::read_volatile((address + i as u32) as *const u8) };
+        }
+        Ok(data)
+    }
+
+    /// Reads a [`u32`] from the information block, which holds factory trim
+    /// values and the unique serial number of the chip. The offset is
+    /// relative to [`INFO_BASE`] and must be 32-bit aligned.
+    ///
+    /// The information block is unlocked for the duration of the read and
+    /// locked again afterwards.
+    pub fn read_info(&self, offset: u32) -> Result<u32, FlashError> {
+        self.check_info_offset(offset)?;
+        while self.is_busy() {}
+        Ok(read_info_word(&self.flc, offset))
+    }
+
+    /// Reads the chip ID from the start of the information block. The
+    /// chip ID contains the factory-programmed unique serial number (USN) of
+    /// the chip, so it is stable and unique for each chip.
+    ///
+    /// See also [`device_id`], which does not require a [`Flc`] instance.
+    pub fn chip_id(&self) -> [u8; CHIP_ID_LEN] {
+        while self.is_busy() {}
+        read_chip_id(&self.flc)
+    }
+
+    /// Puts the flash controller into a known-safe state, e.g. from a panic
+    /// handler. See [`safe_abort`], which does not require a [`Flc`]
+    /// instance.
+    pub fn safe_abort(&self) -> bool {
+        abort(&self.flc)
+    }
+
     /// Erases a page in flash memory.
     ///
     /// # Safety
     /// Care must be taken to not erase the page containing the executing code.
     pub unsafe fn erase_page(&self, address: u32) -> Result<(), FlashError> {
-        self._erase_page(address)
+        self._erase_page(address)?;
+        #[cfg(feature = "erase-counters")]
+        {
+            let count = &self.erase_counts[self.get_page_number(address)? as usize];
+            count.set(count.get().saturating_add(1));
+        }
+        Ok(())
+    }
+
+    /// Returns the number of times a page has been erased since the flash
+    /// controller was constructed (or since the count was last set with
+    /// [`Flc::set_erase_count`]). Only successful erases through
+    /// [`Flc::erase_page`] are counted; writes are not tracked.
+    ///
+    /// The counts are kept in RAM and are lost on reset. To track wear across
+    /// resets, store the counts in flash and restore them with
+    /// [`Flc::set_erase_count`] after constructing the flash controller.
+    ///
+    /// Requires the `erase-counters` feature.
+    #[cfg(feature = "erase-counters")]
+    pub fn erase_count(&self, page_number: u32) -> Result<u32, FlashError> {
+        self.check_page_number(page_number)?;
+        Ok(self.erase_counts[page_number as usize].get())
+    }
+
+    /// Sets the erase count of a page, e.g. to restore counts saved before a
+    /// reset.
+    ///
+    /// Requires the `erase-counters` feature.
+    #[cfg(feature = "erase-counters")]
+    pub fn set_erase_count(&self, page_number: u32, count: u32) -> Result<(), FlashError> {
+        self.check_page_number(page_number)?;
+        self.erase_counts[page_number as usize].set(count);
+        Ok(())
     }
 
     /// Protects a page in flash memory from write or erase operations.
@@ -376,3 +607,299 @@ impl Flc {
         Ok(())
     }
 }
+
+/// Reads the unique device ID of the chip. This is the same value as
+/// [`Flc::chip_id`], but can be read without initializing the flash
+/// controller.
+///
+/// Reading the device ID is always safe: only the access control register of
+/// the information block is written, which does not affect flash writes or
+/// erases. The read waits for any ongoing flash operation to complete.
+///
+/// Example:
+/// ```
+/// let id: [u8; 16] = hal::flc::device_id();
+/// ```
+pub fn device_id() -> [u8; CHIP_ID_LEN] {
+    // Safety: Only the information block access control register is written
+    let flc = unsafe { &*crate::pac::Flc::ptr() };
+    while flc.ctrl().read().pend().is_busy() {}
+    read_chip_id(flc)
+}
+
+/// Puts the flash controller into a known-safe state, so that a reset does
+/// not interrupt a flash operation. This is intended to be called from a
+/// panic handler, where the [`Flc`] instance is usually not available.
+///
+/// This waits up to [`ABORT_TIMEOUT_POLLS`] polls for any in-progress write
+/// or erase to finish, then locks the flash controller and clears its
+/// interrupt flags. Returns [`true`] if the flash controller was idle, or
+/// [`false`] if the operation was still in progress when giving up.
+///
+/// Calling this is always safe: an in-progress operation is never
+/// interrupted, and locking the flash controller only makes later write or
+/// erase attempts fail.
+///
+/// Example:
+/// ```
+/// #[panic_handler]
+/// fn panic(_info: &core::panic::PanicInfo) -> ! {
+///     hal::flc::safe_abort();
+///     cortex_m::peripheral::SCB::sys_reset()
+/// }
+/// ```
+pub fn safe_abort() -> bool {
+    // Safety: Waiting for and locking the flash controller cannot interfere
+    // with an in-progress operation
+    let flc = unsafe { &*crate::pac::Flc::ptr() };
+    abort(flc)
+}
+
+/// Copies the `.flashprog` section from flash into RAM. This must be called
+/// once, before any function in the section runs. See the
+/// [module documentation](self) for the linker script that defines the
+/// section.
+///
+/// ## Safety
+/// The linker script must define `__sflashprog`, `__eflashprog` and
+/// `__siflashprog` as shown in the module documentation, and no function in
+/// the `.flashprog` section may be running.
+#[cfg(feature = "flashprog-linkage")]
+pub unsafe fn init_flashprog() {
+    extern "C" {
+        static mut __sflashprog: u32;
+        static mut __eflashprog: u32;
+        static __siflashprog: u32;
+    }
+    let start = core::ptr::addr_of_mut!(__sflashprog);
+    let end = core::ptr::addr_of_mut!(__eflashprog);
+    let count = end.offset_from(start) as usize;
+    core::ptr::copy_nonoverlapping(core::ptr::addr_of!(__siflashprog), start, count);
+}
+
+/// Defines functions that execute from RAM, by placing them in the
+/// `.flashprog` section used by the `flashprog-linkage` feature (see the
+/// [`flc`](crate::flc) module documentation for the linker script). Without
+/// that feature, the functions are defined as usual and execute from flash.
+///
+/// Functions defined this way are never inlined, so they cannot end up back
+/// in flash through their callers. Any function they call while a write or
+/// erase is in progress must also be in RAM (or inlined). Of the [`Flc`]
+/// code, only the internal functions that start an operation and wait for
+/// it to complete are in RAM. The public methods, such as
+/// [`Flc::write_32`], [`Flc::write_64`], [`Flc::write_128`] and
+/// [`Flc::erase_page`], execute from flash before and after the operation.
+/// They can only be called from a RAM routine if the pages being erased or
+/// written do not hold their code (or the code of any other flash-resident
+/// function that is called, such as `core` functions like `memcpy`).
+///
+/// Example of an update routine that erases and rewrites a data page, where
+/// the page holds no code:
+/// ```
+/// hal::ram_func! {
+///     /// Replace the contents of a page.
+///     fn update_page(flc: &Flc, address: u32, data: &[[u32; 4]]) -> Result<(), FlashError> {
+///         unsafe { flc.erase_page(address)? };
+///         for (i, block) in data.iter().enumerate() {
+///             flc.write_128(address + i as u32 * 16, block)?;
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// unsafe { hal::flc::init_flashprog() };
+/// let flc = Flc::new(p.flc, sys_clk);
+/// update_page(&flc, 0x1006_0000, &[[0x0403_0201, 0x0807_0605, 0x0C0B_0A09, 0x100F_0E0D]])?;
+/// ```
+#[cfg(feature = "flashprog-linkage")]
+#[macro_export]
+macro_rules! ram_func {
+    ($($(#[$meta:meta])* $vis:vis fn $name:ident ($($args:tt)*) $(-> $ret:ty)? $body:block)*) => {
+        $(
+            $(#[$meta])*
+            #[link_section = ".flashprog"]
+            #[inline(never)]
+            $vis fn $name($($args)*) $(-> $ret)? $body
+        )*
+    };
+}
+
+#[cfg(not(feature = "flashprog-linkage"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! ram_func {
+    ($($(#[$meta:meta])* $vis:vis fn $name:ident ($($args:tt)*) $(-> $ret:ty)? $body:block)*) => {
+        $(
+            $(#[$meta])*
+            #[inline(never)]
+            $vis fn $name($($args)*) $(-> $ret)? $body
+        )*
+    };
+}
+
+/// Waits for the flash controller to become idle, then locks it and clears
+/// its interrupt flags.
+#[inline]
+fn abort(flc: &crate::pac::flc::RegisterBlock) -> bool {
+    let mut idle = false;
+    for _ in 0..ABORT_TIMEOUT_POLLS {
+        let ctrl = flc.ctrl().read();
+        if !(ctrl.pend().is_busy()
+            || ctrl.pge().bit_is_set()
+            || ctrl.me().bit_is_set()
+            || ctrl.wr().bit_is_set())
+        {
+            idle = true;
+            break;
+        }
+    }
+    flc.ctrl().modify(|_, w| w.unlock().locked());
+    // Interrupt flags are cleared by writing 0
+    flc.intr()
+        .modify(|_, w| w.done().clear_bit().af().clear_bit());
+    idle
+}
+
+/// Returns the number of the flash page containing `address`, or
+/// [`FlashError::InvalidAddress`] if the address is outside the flash memory.
+#[inline]
+const fn page_number(address: u32) -> Result<u32, FlashError> {
+    if address < FLASH_BASE || address >= FLASH_END {
+        return Err(FlashError::InvalidAddress);
+    }
+    let page_num = (address - FLASH_BASE) / FLASH_PAGE_SIZE;
+    if page_num >= FLASH_PAGE_COUNT {
+        return Err(FlashError::InvalidAddress);
+    }
+    Ok(page_num)
+}
+
+/// Checks that writing `new` over `old` only makes 1 -> 0 bit transitions.
+/// Returns [`false`] if no bits would change, so the write can be skipped.
+#[inline(always)]
+fn check_write(old: &[u32; 4], new: &[u32; 4]) -> Result<bool, FlashError> {
+    let mut changed = false;
+    for (old, new) in old.iter().zip(new) {
+        if old & new != *new {
+            return Err(FlashError::NeedsErase);
+        }
+        changed |= old != new;
+    }
+    Ok(changed)
+}
+
+/// Replaces the words of a 128-bit block starting at `index` with `data`.
+/// Returns [`None`] if the words already hold `data`, so nothing needs to be
+/// written.
+#[inline]
+fn merge_words(mut block: [u32; 4], index: usize, data: &[u32]) -> Option<[u32; 4]> {
+    let words = &mut block[index..index + data.len()];
+    if words == data {
+        return None;
+    }
+    words.copy_from_slice(data);
+    Some(block)
+}
+
+/// Reads the chip ID from the start of the information block.
+#[inline]
+fn read_chip_id(flc: &crate::pac::flc::RegisterBlock) -> [u8; CHIP_ID_LEN] {
+    let mut id = [0; CHIP_ID_LEN];
+    for (i, chunk) in id.chunks_exact_mut(4).enumerate() {
+        chunk.copy_from_slice(&read_info_word(flc, i as u32 * 4).to_le_bytes());
+    }
+    id
+}
+
+/// Reads a word from the information block. The offset must already be
+/// validated.
+#[inline]
+fn read_info_word(flc: &crate::pac::flc::RegisterBlock, offset: u32) -> u32 {
+    // Safety: The unlock sequence is defined in the user guide
+    unsafe {
+        flc.actrl().write(|w| w.bits(0x3A7F_5CA3));
+        flc.actrl().write(|w| w.bits(0xA1E3_4F20));
+        flc.actrl().write(|w| w.bits(0x9608_B2C1));
+    }
+    // Safety: The caller has checked the offset already
+    let data = unsafe { core::ptr::read_volatile((INFO_BASE + offset) as *const u32) };
+    // Safety: Writing any other value locks the information block
+    flc.actrl().write(|w| unsafe { w.bits(0) });
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_number_boundaries() {
+        // First page
+        assert_eq!(page_number(FLASH_BASE), Ok(0));
+        assert_eq!(page_number(FLASH_BASE + FLASH_PAGE_SIZE - 1), Ok(0));
+        assert_eq!(page_number(FLASH_BASE + FLASH_PAGE_SIZE), Ok(1));
+        // Last page
+        assert_eq!(
+            page_number(FLASH_END - FLASH_PAGE_SIZE),
+            Ok(FLASH_PAGE_COUNT - 1)
+        );
+        assert_eq!(page_number(FLASH_END - 1), Ok(FLASH_PAGE_COUNT - 1));
+        // Outside the flash memory
+        assert_eq!(page_number(FLASH_END), Err(FlashError::InvalidAddress));
+        assert_eq!(page_number(FLASH_BASE - 1), Err(FlashError::InvalidAddress));
+        assert_eq!(page_number(0), Err(FlashError::InvalidAddress));
+        assert_eq!(page_number(u32::MAX), Err(FlashError::InvalidAddress));
+    }
+
+    #[test]
+    fn merge_words_no_op() {
+        let block = [0x1111_1111, 0x2222_2222, 0x3333_3333, 0x4444_4444];
+        assert_eq!(merge_words(block, 1, &[0x2222_2222]), None);
+        assert_eq!(
+            merge_words(block, 1, &[0x2222_0000]),
+            Some([0x1111_1111, 0x2222_0000, 0x3333_3333, 0x4444_4444])
+        );
+    }
+
+    #[test]
+    fn merge_words_64_bit_halves() {
+        let erased = [0xFFFF_FFFF; 4];
+        let data = [0x7856_3412, 0xF0DE_BC9A];
+        // Lower half
+        assert_eq!(
+            merge_words(erased, 0, &data),
+            Some([0x7856_3412, 0xF0DE_BC9A, 0xFFFF_FFFF, 0xFFFF_FFFF])
+        );
+        // Upper half
+        assert_eq!(
+            merge_words(erased, 2, &data),
+            Some([0xFFFF_FFFF, 0xFFFF_FFFF, 0x7856_3412, 0xF0DE_BC9A])
+        );
+        // Already written, also if only one word of the half matches
+        let written = [0xFFFF_FFFF, 0xFFFF_FFFF, 0x7856_3412, 0xF0DE_BC9A];
+        assert_eq!(merge_words(written, 2, &data), None);
+        assert_eq!(
+            merge_words(written, 2, &[0x7856_3412, 0]),
+            Some([0xFFFF_FFFF, 0xFFFF_FFFF, 0x7856_3412, 0])
+        );
+    }
+
+    #[test]
+    fn check_write_identical_block() {
+        let block = [0xFFFF_FFFF, 0x1234_5678, 0, 0xFFFF_0000];
+        assert_eq!(check_write(&block, &block), Ok(false));
+    }
+
+    #[test]
+    fn check_write_transitions() {
+        let erased = [0xFFFF_FFFF; 4];
+        // Only 1 -> 0 transitions
+        assert_eq!(
+            check_write(&erased, &[0xFFFF_FFFF, 0x1234_5678, 0xFFFF_FFFF, 0]),
+            Ok(true)
+        );
+        // A 0 -> 1 transition in any word
+        let written = [0xFFFF_FFFF, 0xFFFF_FFFF, 0xFFFF_FFFF, 0xFFFF_FFFE];
+        assert_eq!(check_write(&written, &erased), Err(FlashError::NeedsErase));
+    }
+}
@@ -7,6 +7,7 @@
 
 use core::marker::PhantomData;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum OscillatorSourceEnum {
     /// Internal Primary Oscillator (100 MHz)
     Ipo,
@@ -307,6 +308,9 @@ impl Oscillator<ExternalRtcOscillator, Disabled> {
     }
 }
 
+/// Maximum rated frequency of the system clock (SYS_CLK).
+pub const SYSTEM_CLOCK_MAX_FREQUENCY: u32 = 100_000_000; // 100 MHz
+
 /// System clock setup configuration (source and divider).
 pub struct SystemClockConfig<S: OscillatorSource, D: SystemClockDivider> {
     _source: PhantomData<S>,
@@ -317,6 +321,73 @@ pub struct SystemClockConfig<S: OscillatorSource, D: SystemClockDivider> {
 pub struct SystemClockResults {
     pub sys_clk: Clock<SystemClock>,
     pub pclk: Clock<PeripheralClock>,
+    source: OscillatorSourceEnum,
+    divisor: u32,
+}
+
+impl SystemClockResults {
+    /// Reapply the frozen system clock source and divider after waking from
+    /// a low power mode, and return the resulting clocks. The source
+    /// oscillator is re-enabled if it was stopped.
+    ///
+    /// Which clock state survives each low power mode:
+    /// - SLEEP: Everything is retained, so no restoration is needed.
+    /// - DEEPSLEEP and UPM: The clock configuration registers are retained,
+    ///   but the system oscillator is stopped while asleep and the system
+    ///   clock may fall back to the ISO on wake. Call this function before
+    ///   using any peripheral that depends on the clock frequency.
+    /// - BACKUP, STORAGE, and POWER DOWN: Waking up resets the chip, so the
+    ///   clocks must be configured again from scratch.
+    ///
+    /// ERTCO cannot be selected as the system clock source by this HAL, so
+    /// for a configuration frozen with the ERTCO as its source, the clock
+    /// registers are left untouched and the frozen clocks are returned.
+    ///
+    /// Example:
+    /// ```
+    /// let clks = gcr.sys_clk.set_source(&mut gcr.reg, &ipo).freeze();
+    /// // ... enter and wake from DEEPSLEEP ...
+    /// let clks = clks.restore_after_wake(&mut gcr.reg);
+    /// ```
+    pub fn restore_after_wake(&self, reg: &mut super::GcrRegisters) -> SystemClockResults {
+        let results = SystemClockResults {
+            sys_clk: self.sys_clk,
+            pclk: self.pclk,
+            source: self.source,
+            divisor: self.divisor,
+        };
+        match self.source {
+            OscillatorSourceEnum::Ipo => {
+                reg.gcr.clkctrl().modify(|_, w| w.ipo_en().set_bit());
+                while reg.gcr.clkctrl().read().ipo_rdy().bit_is_clear() {}
+                reg.gcr.clkctrl().modify(|_, w| w.sysclk_sel().ipo());
+            }
+            OscillatorSourceEnum::Iso => {
+                reg.gcr.clkctrl().modify(|_, w| w.iso_en().set_bit());
+                while reg.gcr.clkctrl().read().iso_rdy().bit_is_clear() {}
+                reg.gcr.clkctrl().modify(|_, w| w.sysclk_sel().iso());
+            }
+            OscillatorSourceEnum::Ibro => {
+                // IBRO is always enabled
+                while reg.gcr.clkctrl().read().ibro_rdy().bit_is_clear() {}
+                reg.gcr.clkctrl().modify(|_, w| w.sysclk_sel().ibro());
+            }
+            OscillatorSourceEnum::Ertco => {
+                // The system clock was never switched to the ERTCO
+                return results;
+            }
+        }
+        while reg.gcr.clkctrl().read().sysclk_rdy().bit_is_clear() {}
+        // The divider field holds log2 of the divisor
+        let div_bits = self.divisor.trailing_zeros() as u8;
+        // Safety: The divisor is a power of two from 1 to 128 (enforced by
+        // SystemClockDivider), so its log2 is a valid divider value
+        reg.gcr
+            .clkctrl()
+            .modify(|_, w| unsafe { w.sysclk_div().bits(div_bits) });
+        while reg.gcr.clkctrl().read().sysclk_rdy().bit_is_clear() {}
+        results
+    }
 }
 
 impl<S, D> SystemClockConfig<S, D>
@@ -402,17 +473,352 @@ where
         }
     }
 
+    /// System clock frequency of this configuration, checked at compile
+    /// time to be within the rated range.
+    const SYS_CLK_FREQUENCY: u32 = {
+        let frequency = S::BASE_FREQUENCY / D::DIVISOR;
+        assert!(
+            frequency <= SYSTEM_CLOCK_MAX_FREQUENCY,
+            "System clock frequency exceeds the rated maximum"
+        );
+        assert!(frequency > 0, "System clock frequency must not be zero");
+        frequency
+    };
+
+    /// Returns [`true`] if the peripheral clock (PCLK) of this configuration
+    /// is at least `min_frequency` (in Hz). This can be used in a constant
+    /// assertion to check that the peripheral clock is fast enough for a
+    /// peripheral before the configuration is used.
+    ///
+    /// Example:
+    /// ```
+    /// type Config = SystemClockConfig<InternalPrimaryOscillator, Div4>;
+    /// // Fails to compile if PCLK is slower than 1 MHz
+    /// const _: () = assert!(Config::pclk_at_least(1_000_000));
+    /// ```
+    pub const fn pclk_at_least(min_frequency: u32) -> bool {
+        Self::SYS_CLK_FREQUENCY / 2 >= min_frequency
+    }
+
     /// Freeze the system clock configuration and return configured clocks.
+    ///
+    /// The resulting system clock frequency is checked at compile time, so a
+    /// configuration faster than [`SYSTEM_CLOCK_MAX_FREQUENCY`] does not
+    /// compile.
     pub const fn freeze(self) -> SystemClockResults {
         SystemClockResults {
             sys_clk: Clock::<SystemClock> {
                 _src: PhantomData,
-                frequency: S::BASE_FREQUENCY / D::DIVISOR,
+                frequency: Self::SYS_CLK_FREQUENCY,
             },
             pclk: Clock::<PeripheralClock> {
                 _src: PhantomData,
-                frequency: (S::BASE_FREQUENCY / D::DIVISOR) / 2,
+                frequency: Self::SYS_CLK_FREQUENCY / 2,
             },
+            source: S::SOURCE,
+            divisor: D::DIVISOR,
         }
     }
 }
+
+/// A system clock source and divider found by [`find_pclk_config`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PclkPlan {
+    /// Source oscillator of the system clock.
+    pub source: OscillatorSourceEnum,
+    /// Divider of the system clock (1 to 128).
+    pub divisor: u32,
+    /// Resulting peripheral clock (PCLK) frequency (in Hz).
+    pub pclk_frequency: u32,
+    /// Difference between the resulting and the target PCLK frequency (in
+    /// Hz).
+    pub error: u32,
+}
+
+/// Searches all supported system clock sources (IPO, ISO, and IBRO) and
+/// dividers for the peripheral clock (PCLK) frequency closest to `target`
+/// (in Hz). Returns [`None`] if the closest frequency is more than
+/// `tolerance` Hz away from the target.
+///
+/// When several configurations are equally close, the one with the fastest
+/// system clock is returned. The result maps directly to a typed
+/// [`SystemClockConfig`]: e.g. a source of [`OscillatorSourceEnum::Ipo`] and
+/// a divisor of `4` is `SystemClockConfig<InternalPrimaryOscillator, Div4>`.
+///
+/// This is a `const fn`, so the search can be done at compile time.
+///
+/// Example:
+/// ```
+/// // Find a 25 MHz PCLK, allowing up to 1 kHz of error
+/// const PLAN: PclkPlan = match find_pclk_config(25_000_000, 1_000) {
+///     Some(plan) => plan,
+///     None => panic!("No clock configuration for 25 MHz"),
+/// };
+/// // PLAN is IPO / 2 (SYS_CLK = 50 MHz), with an error of 0 Hz
+/// ```
+pub const fn find_pclk_config(target: u32, tolerance: u32) -> Option<PclkPlan> {
+    const SOURCES: [(OscillatorSourceEnum, u32); 3] = [
+        (
+            OscillatorSourceEnum::Ipo,
+            InternalPrimaryOscillator::BASE_FREQUENCY,
+        ),
+        (
+            OscillatorSourceEnum::Iso,
+            InternalSecondaryOscillator::BASE_FREQUENCY,
+        ),
+        (
+            OscillatorSourceEnum::Ibro,
+            InternalBaudRateOscillator::BASE_FREQUENCY,
+        ),
+    ];
+    let mut best: Option<PclkPlan> = None;
+    let mut i = 0;
+    while i < SOURCES.len() {
+        let (source, base_frequency) = SOURCES[i];
+        let mut divisor = 1;
+        while divisor <= 128 {
+            let sys_clk = base_frequency / divisor;
+            if sys_clk <= SYSTEM_CLOCK_MAX_FREQUENCY {
+                let pclk_frequency = sys_clk / 2;
+                let error = pclk_frequency.abs_diff(target);
+                let better = match best {
+                    Some(plan) => {
+                        error < plan.error
+                            || (error == plan.error && pclk_frequency > plan.pclk_frequency)
+                    }
+                    None => true,
+                };
+                if better {
+                    best = Some(PclkPlan {
+                        source,
+                        divisor,
+                        pclk_frequency,
+                        error,
+                    });
+                }
+            }
+            divisor *= 2;
+        }
+        i += 1;
+    }
+    match best {
+        Some(plan) if plan.error <= tolerance => Some(plan),
+        _ => None,
+    }
+}
+
+/// Enable the IPO and run the system clock from it undivided.
+///
+/// Applies the following configuration:
+/// - IPO enabled
+/// - SYS_CLK: IPO / 1 = 100 MHz
+/// - PCLK: SYS_CLK / 2 = 50 MHz
+///
+/// Example:
+/// ```
+/// let mut gcr = hal::gcr::Gcr::new(p.gcr, p.lpgcr);
+/// let clks = hal::gcr::clocks::configure_100mhz_ipo(
+///     &mut gcr.reg,
+///     gcr.osc_guards.ipo,
+///     gcr.sys_clk,
+/// );
+/// ```
+pub fn configure_100mhz_ipo<S: OscillatorSource, D: SystemClockDivider>(
+    reg: &mut super::GcrRegisters,
+    guard: OscillatorGuard<InternalPrimaryOscillator>,
+    sys_clk: SystemClockConfig<S, D>,
+) -> SystemClockResults {
+    let ipo = Ipo::new(guard).enable(reg);
+    sys_clk
+        .set_source(reg, &ipo)
+        .set_divider::<Div1>(reg)
+        .freeze()
+}
+
+/// Enable the ISO and run the system clock from it undivided.
+///
+/// Applies the following configuration:
+/// - ISO enabled
+/// - SYS_CLK: ISO / 1 = 60 MHz
+/// - PCLK: SYS_CLK / 2 = 30 MHz
+///
+/// See [`configure_100mhz_ipo`] for an example.
+pub fn configure_60mhz_iso<S: OscillatorSource, D: SystemClockDivider>(
+    reg: &mut super::GcrRegisters,
+    guard: OscillatorGuard<InternalSecondaryOscillator>,
+    sys_clk: SystemClockConfig<S, D>,
+) -> SystemClockResults {
+    let iso = Iso::new(guard).enable(reg);
+    sys_clk
+        .set_source(reg, &iso)
+        .set_divider::<Div1>(reg)
+        .freeze()
+}
+
+/// Run the system clock from the IBRO undivided, for low power operation.
+///
+/// Applies the following configuration:
+/// - IBRO enabled (always on)
+/// - SYS_CLK: IBRO / 1 = 7.3728 MHz
+/// - PCLK: SYS_CLK / 2 = 3.6864 MHz
+///
+/// See [`configure_100mhz_ipo`] for an example.
+pub fn configure_7mhz_ibro<S: OscillatorSource, D: SystemClockDivider>(
+    reg: &mut super::GcrRegisters,
+    guard: OscillatorGuard<InternalBaudRateOscillator>,
+    sys_clk: SystemClockConfig<S, D>,
+) -> SystemClockResults {
+    let ibro = Ibro::new(guard).enable(reg);
+    sys_clk
+        .set_source(reg, &ibro)
+        .set_divider::<Div1>(reg)
+        .freeze()
+}
+
+impl Clock<SystemClock> {
+    /// Read the current system clock (SYS_CLK) frequency from the hardware.
+    ///
+    /// Unlike [`Clock::frequency`], this reflects any changes made to the
+    /// system clock source or divider after the configuration was frozen.
+    /// If the system clock source is not one of the supported oscillators,
+    /// the frozen frequency is returned instead.
+    pub fn current_frequency(&self) -> u32 {
+        use crate::pac::gcr::clkctrl::SysclkSel;
+        // Safety: Concurrent read access to the GCR clock control register is safe
+        let gcr = unsafe { &*crate::pac::Gcr::ptr() };
+        let clkctrl = gcr.clkctrl().read();
+        let base_frequency = match clkctrl.sysclk_sel().variant() {
+            Some(SysclkSel::Ipo) => InternalPrimaryOscillator::BASE_FREQUENCY,
+            Some(SysclkSel::Iso) => InternalSecondaryOscillator::BASE_FREQUENCY,
+            Some(SysclkSel::Ibro) => InternalBaudRateOscillator::BASE_FREQUENCY,
+            Some(SysclkSel::Ertco) => ExternalRtcOscillator::BASE_FREQUENCY,
+            _ => return self.frequency,
+        };
+        base_frequency >> clkctrl.sysclk_div().bits()
+    }
+}
+
+/// Delay provider that busy-waits by counting CPU cycles with the DWT cycle
+/// counter, implementing [`embedded_hal::delay::DelayNs`] without using a
+/// timer peripheral.
+///
+/// The system clock frequency is read from the hardware at the start of
+/// every delay, so delays remain accurate if the system clock is changed.
+///
+/// Delays are only as accurate as the number of cycles spent outside of the
+/// counting loop: reading the frequency and converting the duration takes on
+/// the order of tens of cycles, so very short delays (below roughly 1 µs at
+/// 100 MHz) will be noticeably longer than requested. Delays may also be
+/// extended by interrupts. Delays are never shorter than requested.
+///
+/// Example:
+/// ```
+/// let mut core = cortex_m::Peripherals::take().unwrap();
+/// let mut delay = CycleDelay::new(core.DWT, &mut core.DCB, clks.sys_clk);
+/// delay.delay_ms(10);
+/// ```
+pub struct CycleDelay {
+    _dwt: cortex_m::peripheral::DWT,
+    sys_clk: Clock<SystemClock>,
+}
+
+impl CycleDelay {
+    /// Create a new cycle counter delay provider. This enables the DWT cycle
+    /// counter.
+    pub fn new(
+        mut dwt: cortex_m::peripheral::DWT,
+        dcb: &mut cortex_m::peripheral::DCB,
+        sys_clk: Clock<SystemClock>,
+    ) -> Self {
+        dcb.enable_trace();
+        dwt.enable_cycle_counter();
+        Self { _dwt: dwt, sys_clk }
+    }
+
+    /// Busy-wait for the given number of CPU cycles.
+    #[inline]
+    pub fn delay_cycles(&mut self, mut cycles: u64) {
+        // Wait in chunks so that the 32-bit cycle counter cannot wrap around
+        // more than once while waiting
+        while cycles > 0 {
+            let chunk = cycles.min(u32::MAX as u64 / 2) as u32;
+            let start = cortex_m::peripheral::DWT::cycle_count();
+            while cortex_m::peripheral::DWT::cycle_count().wrapping_sub(start) < chunk {}
+            cycles -= chunk as u64;
+        }
+    }
+
+    /// Convert a duration in units of `1 / scale` seconds into CPU cycles,
+    /// rounding up.
+    #[inline]
+    fn cycles_for(&self, duration: u32, scale: u64) -> u64 {
+        let frequency = self.sys_clk.current_frequency() as u64;
+        (duration as u64 * frequency).div_ceil(scale)
+    }
+}
+
+impl embedded_hal::delay::DelayNs for CycleDelay {
+    #[inline]
+    fn delay_ns(&mut self, ns: u32) {
+        let cycles = self.cycles_for(ns, 1_000_000_000);
+        self.delay_cycles(cycles);
+    }
+
+    #[inline]
+    fn delay_us(&mut self, us: u32) {
+        let cycles = self.cycles_for(us, 1_000_000);
+        self.delay_cycles(cycles);
+    }
+
+    #[inline]
+    fn delay_ms(&mut self, ms: u32) {
+        let cycles = self.cycles_for(ms, 1_000);
+        self.delay_cycles(cycles);
+    }
+}
+
+/// # Profiling
+///
+/// Helpers for measuring code in CPU cycles with the DWT cycle counter, and
+/// converting cycles to time using the system clock frequency.
+///
+/// The cycle counter is 32 bits wide and counts at the system clock
+/// frequency, so it wraps around every `2^32 / f` seconds: about 43 s at
+/// 100 MHz, 72 s at 60 MHz and 583 s at 7.3728 MHz. Differences computed
+/// with [`u32::wrapping_sub`] are correct as long as the measured code runs
+/// for less than one period.
+///
+/// Example:
+/// ```
+/// let mut core = cortex_m::Peripherals::take().unwrap();
+/// hal::gcr::clocks::profiling::enable(&mut core.DWT, &mut core.DCB);
+/// let start = hal::gcr::clocks::profiling::cycles();
+/// // Code to measure
+/// let elapsed = hal::gcr::clocks::profiling::cycles().wrapping_sub(start);
+/// let ns = hal::gcr::clocks::profiling::cycles_to_ns(elapsed, &clks.sys_clk);
+/// ```
+pub mod profiling {
+    use super::{Clock, SystemClock};
+
+    /// Enable the DWT cycle counter. This is also done by
+    /// [`CycleDelay::new`](super::CycleDelay::new).
+    pub fn enable(dwt: &mut cortex_m::peripheral::DWT, dcb: &mut cortex_m::peripheral::DCB) {
+        dcb.enable_trace();
+        dwt.enable_cycle_counter();
+    }
+
+    /// Returns the current value of the DWT cycle counter. The counter must
+    /// be enabled with [`enable`].
+    #[inline(always)]
+    pub fn cycles() -> u32 {
+        cortex_m::peripheral::DWT::cycle_count()
+    }
+
+    /// Convert a number of CPU cycles into nanoseconds, rounding down. The
+    /// system clock frequency is read from the hardware (see
+    /// [`Clock::current_frequency`]), so the system clock must not have
+    /// changed since the cycles were counted.
+    pub fn cycles_to_ns(cycles: u32, sys_clk: &Clock<SystemClock>) -> u64 {
+        cycles as u64 * 1_000_000_000 / sys_clk.current_frequency() as u64
+    }
+}
@@ -40,12 +40,43 @@ pub trait ClockForPeripheral {
     type ValidatedGcrRegisterType: GcrRegisterType;
     unsafe fn enable_clock(&self, gcr: &mut Self::ValidatedGcrRegisterType);
     unsafe fn disable_clock(&self, gcr: &mut Self::ValidatedGcrRegisterType);
+    fn is_clock_enabled(&self, gcr: &Self::ValidatedGcrRegisterType) -> bool;
 }
 
 // Extension trait for peripheral resets.
+//
+// The reset bits of the MAX78000 are self-clearing: setting a bit starts the
+// reset, and the hardware clears the bit once the reset is complete. The
+// length of the reset pulse is therefore determined by the hardware, and no
+// peripheral needs the reset to be held for a minimum time. `reset` covers
+// the common case. `assert_reset` and `deassert_reset` split it into its two
+// halves, e.g. to reset several peripherals at once and only wait once, and
+// `reset_with_delay` adds a hold time after the reset for peripherals (or
+// external devices attached to them) that need time to settle.
 pub trait ResetForPeripheral {
     type ValidatedGcrRegisterType: GcrRegisterType;
     unsafe fn reset(&self, resets: &mut Self::ValidatedGcrRegisterType);
+    /// Starts a reset of the peripheral without waiting for it to complete.
+    ///
+    /// ## Safety
+    /// The peripheral must not be in use until the reset is complete.
+    unsafe fn assert_reset(&self, resets: &mut Self::ValidatedGcrRegisterType);
+    /// Waits for a reset started by `assert_reset` to complete.
+    ///
+    /// ## Safety
+    /// The peripheral must not be in use until the reset is complete.
+    unsafe fn deassert_reset(&self, resets: &mut Self::ValidatedGcrRegisterType);
+    fn is_reset_active(&self, resets: &Self::ValidatedGcrRegisterType) -> bool;
+    /// Resets the peripheral, then waits for `hold_us` microseconds.
+    ///
+    /// ## Safety
+    /// The peripheral must not be in use when initiating a reset.
+    unsafe fn reset_with_delay(
+        &self,
+        resets: &mut Self::ValidatedGcrRegisterType,
+        delay: &mut impl embedded_hal::delay::DelayNs,
+        hold_us: u32,
+    );
 }
 
 macro_rules! generate_clock {
@@ -58,15 +89,25 @@ macro_rules! generate_clock {
             /// ## Safety
             /// It is recommended that this function is only called
             /// through the constructor of a HAL peripheral, rather than
-            /// directly by the user. If called directly, the user should
-            /// ensure that the peripheral clock is not already enabled.
+            /// directly by the user.
+            ///
+            /// If the peripheral clock is already enabled (e.g. when a
+            /// peripheral is reconstructed after a warm reset), this does
+            /// nothing.
             unsafe fn enable_clock(&self, gcr: &mut Self::ValidatedGcrRegisterType) {
+                if self.is_clock_enabled(gcr) {
+                    return;
+                }
                 gcr.$PCLKDISN().modify(|_, w| w.$PCLK_FIELD().clear_bit());
                 while gcr.$PCLKDISN().read().$PCLK_FIELD().bit_is_set() {}
             }
 
             /// Disables the peripheral clock.
             ///
+            /// Peripherals that share a clock are all stopped: disabling the
+            /// clock of any pulse train channel (`Pt0` to `Pt3`) stops every
+            /// channel.
+            ///
             /// ## Safety
             /// It is recommended that this function is only called
             /// through the constructor of a HAL peripheral, rather than
@@ -76,6 +117,11 @@ macro_rules! generate_clock {
                 gcr.$PCLKDISN().modify(|_, w| w.$PCLK_FIELD().set_bit());
                 while gcr.$PCLKDISN().read().$PCLK_FIELD().bit_is_clear() {}
             }
+
+            /// Returns [`true`] if the peripheral clock is enabled.
+            fn is_clock_enabled(&self, gcr: &Self::ValidatedGcrRegisterType) -> bool {
+                gcr.$PCLKDISN().read().$PCLK_FIELD().bit_is_clear()
+            }
         }
     };
 }
@@ -94,6 +140,50 @@ macro_rules! generate_reset {
                 gcr.$RST_REG().modify(|_, w| w.$RST_REG_FIELD().set_bit());
                 while gcr.$RST_REG().read().$RST_REG_FIELD().bit_is_set() {}
             }
+
+            /// Starts a reset of the peripheral without waiting for it to
+            /// complete. Call [`ResetForPeripheral::deassert_reset`] before
+            /// using the peripheral again.
+            ///
+            /// ## Safety
+            /// User should ensure that the peripheral is not in use when
+            /// initiating a reset of a peripheral.
+            unsafe fn assert_reset(&self, gcr: &mut Self::ValidatedGcrRegisterType) {
+                gcr.$RST_REG().modify(|_, w| w.$RST_REG_FIELD().set_bit());
+            }
+
+            /// Waits for a reset started with
+            /// [`ResetForPeripheral::assert_reset`] to complete. The reset
+            /// bit is cleared by the hardware, so this cannot end the reset
+            /// early.
+            ///
+            /// ## Safety
+            /// User should ensure that the peripheral is not in use until
+            /// the reset is complete.
+            unsafe fn deassert_reset(&self, gcr: &mut Self::ValidatedGcrRegisterType) {
+                while gcr.$RST_REG().read().$RST_REG_FIELD().bit_is_set() {}
+            }
+
+            /// Returns [`true`] if a reset of the peripheral is in progress.
+            fn is_reset_active(&self, gcr: &Self::ValidatedGcrRegisterType) -> bool {
+                gcr.$RST_REG().read().$RST_REG_FIELD().bit_is_set()
+            }
+
+            /// Resets the peripheral, then waits a further `hold_us`
+            /// microseconds before returning.
+            ///
+            /// ## Safety
+            /// User should ensure that the peripheral is not in use when
+            /// initiating a reset of a peripheral.
+            unsafe fn reset_with_delay(
+                &self,
+                gcr: &mut Self::ValidatedGcrRegisterType,
+                delay: &mut impl embedded_hal::delay::DelayNs,
+                hold_us: u32,
+            ) {
+                self.reset(gcr);
+                delay.delay_us(hold_us);
+            }
         }
     };
 }
@@ -113,7 +203,11 @@ generate_clock!(I2c2, Gcr, pclkdis1, i2c2);
 generate_clock!(I2s, Gcr, pclkdis1, i2s);
 generate_clock!(Lpcmp, Lpgcr, pclkdis, lpcomp);
 generate_clock!(Owm, Gcr, pclkdis1, owm);
+// All pulse train channels share one peripheral clock
 generate_clock!(Pt0, Gcr, pclkdis0, pt);
+generate_clock!(Pt1, Gcr, pclkdis0, pt);
+generate_clock!(Pt2, Gcr, pclkdis0, pt);
+generate_clock!(Pt3, Gcr, pclkdis0, pt);
 generate_clock!(Sema, Gcr, pclkdis1, smphr);
 generate_clock!(Spi0, Gcr, pclkdis1, spi0);
 generate_clock!(Spi1, Gcr, pclkdis0, spi1);
@@ -131,6 +225,106 @@ generate_clock!(Uart3, Lpgcr, pclkdis, uart3);
 generate_clock!(Wdt0, Gcr, pclkdis1, wdt0);
 generate_clock!(Wdt1, Lpgcr, pclkdis, wdt1);
 
+macro_rules! generate_clock_gates {
+    ($($GATE:ident => $GCR_REG:ident, $PCLKDISN:ident, $PCLK_FIELD:ident;)*) => {
+        /// Peripheral clocks that can be gated with
+        /// [`GcrRegisters::gate_clock`] and [`GcrRegisters::gate_all_except`].
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub enum ClockGate {
+            $($GATE,)*
+        }
+
+        impl ClockGate {
+            /// All gateable peripheral clocks.
+            pub const ALL: &'static [ClockGate] = &[$(ClockGate::$GATE,)*];
+        }
+
+        impl GcrRegisters {
+            #[doc(hidden)]
+            #[inline(always)]
+            fn _gate_clock(&mut self, gate: ClockGate) {
+                match gate {
+                    $(ClockGate::$GATE => {
+                        self.$GCR_REG.$PCLKDISN().modify(|_, w| w.$PCLK_FIELD().set_bit());
+                        while self.$GCR_REG.$PCLKDISN().read().$PCLK_FIELD().bit_is_clear() {}
+                    })*
+                }
+            }
+        }
+    };
+}
+
+generate_clock_gates! {
+    Adc => gcr, pclkdis0, adc;
+    Aes => gcr, pclkdis1, aes;
+    Crc => gcr, pclkdis1, crc;
+    Dma => gcr, pclkdis0, dma;
+    Gpio0 => gcr, pclkdis0, gpio0;
+    Gpio1 => gcr, pclkdis0, gpio1;
+    Gpio2 => lpgcr, pclkdis, gpio2;
+    I2c0 => gcr, pclkdis0, i2c0;
+    I2c1 => gcr, pclkdis0, i2c1;
+    I2c2 => gcr, pclkdis1, i2c2;
+    I2s => gcr, pclkdis1, i2s;
+    Lpcmp => lpgcr, pclkdis, lpcomp;
+    Owm => gcr, pclkdis1, owm;
+    Pt => gcr, pclkdis0, pt;
+    Sema => gcr, pclkdis1, smphr;
+    Spi0 => gcr, pclkdis1, spi0;
+    Spi1 => gcr, pclkdis0, spi1;
+    Tmr0 => gcr, pclkdis0, tmr0;
+    Tmr1 => gcr, pclkdis0, tmr1;
+    Tmr2 => gcr, pclkdis0, tmr2;
+    Tmr3 => gcr, pclkdis0, tmr3;
+    Tmr4 => lpgcr, pclkdis, tmr4;
+    Tmr5 => lpgcr, pclkdis, tmr5;
+    Trng => gcr, pclkdis1, trng;
+    Uart0 => gcr, pclkdis0, uart0;
+    Uart1 => gcr, pclkdis0, uart1;
+    Uart2 => gcr, pclkdis1, uart2;
+    Uart3 => lpgcr, pclkdis, uart3;
+    Wdt0 => gcr, pclkdis1, wdt0;
+    Wdt1 => lpgcr, pclkdis, wdt1;
+}
+
+/// # Clock Gating
+/// These methods gate (disable) peripheral clocks to reduce power
+/// consumption. A gated clock is enabled again when its HAL peripheral is
+/// constructed.
+///
+/// Gating the clock of a peripheral that is in use stops the peripheral
+/// without resetting it, and HAL methods that wait on the peripheral may
+/// never return. In particular, do not gate the clock of:
+/// - A GPIO port with pins in use, since input levels stop updating and pin
+///   interrupts no longer fire
+/// - A timer backing a [`crate::tmr::Monotonic`], since time stops counting
+/// - A UART, I2S, or 1-Wire peripheral with a transfer in progress, since
+///   the transfer stalls
+/// - The pulse trains ([`ClockGate::Pt`]) while any channel is running, since
+///   all channels share one peripheral clock
+impl GcrRegisters {
+    /// Gate a single peripheral clock.
+    pub fn gate_clock(&mut self, gate: ClockGate) {
+        self._gate_clock(gate);
+    }
+
+    /// Gate every peripheral clock except those in `keep`.
+    ///
+    /// Example:
+    /// ```
+    /// use hal::gcr::ClockGate;
+    /// // Keep only the console UART and its pins running
+    /// gcr.reg.gate_all_except(&[ClockGate::Gpio0, ClockGate::Uart0]);
+    /// ```
+    pub fn gate_all_except(&mut self, keep: &[ClockGate]) {
+        for gate in ClockGate::ALL {
+            if !keep.contains(gate) {
+                self._gate_clock(*gate);
+            }
+        }
+    }
+}
+
 // TODO: add system, peripheral, and soft resets
 generate_reset!(Adc, Gcr, rst0, adc);
 generate_reset!(Aes, Gcr, rst1, aes);
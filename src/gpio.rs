@@ -1,5 +1,8 @@
 //! General Purpose Input/Output (GPIO)
+use core::cell::RefCell;
 use core::marker::PhantomData;
+use cortex_m::interrupt::{self, Mutex};
+use embedded_hal::delay::DelayNs;
 use embedded_hal::digital::{ErrorType, InputPin, OutputPin, StatefulOutputPin};
 use paste::paste;
 
@@ -34,7 +37,17 @@ impl PowerSupply for Vddio {}
 impl PowerSupply for Vddioh {}
 
 /// Marker trait for GPIO pin input pad modes.
-pub trait PadMode: crate::Sealed {}
+pub trait PadMode: crate::Sealed {
+    /// Value of the pad control bit in `PADCTRL0` (pull-up).
+    #[doc(hidden)]
+    const PADCTRL0: bool;
+    /// Value of the pad control bit in `PADCTRL1` (pull-down).
+    #[doc(hidden)]
+    const PADCTRL1: bool;
+    /// Value of the pull strength select bit in `PS` (strong pull).
+    #[doc(hidden)]
+    const PS: bool;
+}
 
 pub struct HighImpedance;
 pub struct PullUpWeak;
@@ -48,14 +61,38 @@ impl crate::Sealed for PullUpStrong {}
 impl crate::Sealed for PullDownWeak {}
 impl crate::Sealed for PullDownStrong {}
 
-impl PadMode for HighImpedance {}
-impl PadMode for PullUpWeak {}
-impl PadMode for PullUpStrong {}
-impl PadMode for PullDownWeak {}
-impl PadMode for PullDownStrong {}
+impl PadMode for HighImpedance {
+    const PADCTRL0: bool = false;
+    const PADCTRL1: bool = false;
+    const PS: bool = false;
+}
+impl PadMode for PullUpWeak {
+    const PADCTRL0: bool = true;
+    const PADCTRL1: bool = false;
+    const PS: bool = false;
+}
+impl PadMode for PullUpStrong {
+    const PADCTRL0: bool = true;
+    const PADCTRL1: bool = false;
+    const PS: bool = true;
+}
+impl PadMode for PullDownWeak {
+    const PADCTRL0: bool = false;
+    const PADCTRL1: bool = true;
+    const PS: bool = false;
+}
+impl PadMode for PullDownStrong {
+    const PADCTRL0: bool = false;
+    const PADCTRL1: bool = true;
+    const PS: bool = true;
+}
 
 /// Marker trait for GPIO pin output drive strengths.
-pub trait DriveStrength: crate::Sealed {}
+pub trait DriveStrength: crate::Sealed {
+    /// Value of the drive strength select bits (`DS1:DS0`).
+    #[doc(hidden)]
+    const BITS: u8;
+}
 
 pub struct Strength0;
 pub struct Strength1;
@@ -67,10 +104,44 @@ impl crate::Sealed for Strength1 {}
 impl crate::Sealed for Strength2 {}
 impl crate::Sealed for Strength3 {}
 
-impl DriveStrength for Strength0 {}
-impl DriveStrength for Strength1 {}
-impl DriveStrength for Strength2 {}
-impl DriveStrength for Strength3 {}
+impl DriveStrength for Strength0 {
+    const BITS: u8 = 0;
+}
+impl DriveStrength for Strength1 {
+    const BITS: u8 = 1;
+}
+impl DriveStrength for Strength2 {
+    const BITS: u8 = 2;
+}
+impl DriveStrength for Strength3 {
+    const BITS: u8 = 3;
+}
+
+/// Returns the values of the `DS1` and `DS0` bits of a drive strength.
+#[inline(always)]
+const fn drive_strength_bits<DRIVE: DriveStrength>() -> (u32, u32) {
+    (((DRIVE::BITS >> 1) & 1) as u32, (DRIVE::BITS & 1) as u32)
+}
+
+/// Write to an atomic output register of a GPIO port.
+#[derive(Debug, PartialEq)]
+enum OutWrite {
+    /// Write the mask to `OUT_SET`.
+    Set(u32),
+    /// Write the mask to `OUT_CLR`.
+    Clear(u32),
+}
+
+/// Returns the write that toggles pin `n`, given the value of the `OUT`
+/// register. The GPIO ports have no output toggle register.
+#[inline(always)]
+const fn toggle_write(out: u32, n: u8) -> OutWrite {
+    if out & (1 << n) != 0 {
+        OutWrite::Clear(1 << n)
+    } else {
+        OutWrite::Set(1 << n)
+    }
+}
 
 /// Zero-sized abstraction type for a GPIO pin.
 ///
@@ -79,6 +150,29 @@ impl DriveStrength for Strength3 {}
 /// - `P` is the GPIO port number (e.g. `0` for `Gpio0`, `1` for `Gpio1`, etc.)
 /// - `N` is the GPIO pin number.
 /// - `MODE` is one of the pin modes (e.g. `Input`, `InputOutput`, `Af1`, `Af2`).
+/// - `PAD` is the pull resistor of an input pin (e.g. `HighImpedance`,
+///   `PullUpWeak`), set with [`Pin::into_pull_up`] and similar methods.
+/// - `DRIVE` is the output drive strength of an input/output pin (e.g.
+///   `Strength0`), set with [`Pin::into_drive_strength`].
+///
+/// ## Ownership
+/// Pins can only be obtained by splitting a GPIO peripheral, which consumes
+/// the peripheral. Each physical pin is therefore owned by exactly one
+/// `Pin` value, and handing a pin to another peripheral (such as a UART)
+/// moves it out of reach of the rest of the program.
+///
+/// A pin cannot be constructed directly:
+/// ```compile_fail,E0624
+/// let pin = max7800x_hal::gpio::Pin::<0, 0>::new();
+/// ```
+///
+/// A GPIO peripheral cannot be split twice:
+/// ```compile_fail,E0382
+/// fn split_twice(gpio0: max7800x_hal::gpio::Gpio0) {
+///     let pins = gpio0.split();
+///     let pins_again = gpio0.split();
+/// }
+/// ```
 pub struct Pin<
     const P: u8,
     const N: u8,
@@ -94,7 +188,9 @@ pub struct Pin<
 }
 
 /// Default methods that should work across all pin modes.
-impl<const P: u8, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
+impl<const P: u8, const N: u8, MODE: PinMode, PAD: PadMode, DRIVE: DriveStrength>
+    Pin<P, N, MODE, Vddio, PAD, DRIVE>
+{
     const fn new() -> Self {
         Self {
             _mode: PhantomData,
@@ -120,6 +216,40 @@ impl<const P: u8, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
         gpio.outen_clr().write(|w| unsafe { w.bits(1 << N) });
     }
 
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _set_pad<NEW: PadMode>(&mut self) {
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        let set = |bits: u32, value: bool| {
+            if value {
+                bits | (1 << N)
+            } else {
+                bits & !(1 << N)
+            }
+        };
+        // The pad control registers have no atomic set and clear registers,
+        // so they are modified in a critical section
+        interrupt::free(|_| {
+            // Safety: Only the bit of this pin is modified
+            gpio.ps()
+                .modify(|r, w| unsafe { w.bits(set(r.bits(), NEW::PS)) });
+            gpio.padctrl0()
+                .modify(|r, w| unsafe { w.bits(set(r.bits(), NEW::PADCTRL0)) });
+            gpio.padctrl1()
+                .modify(|r, w| unsafe { w.bits(set(r.bits(), NEW::PADCTRL1)) });
+        });
+    }
+
+    /// Removes the pull resistor of the pin, if any, before it leaves the
+    /// input mode.
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _clear_pad(&mut self) {
+        if PAD::PADCTRL0 || PAD::PADCTRL1 {
+            self._set_pad::<HighImpedance>();
+        }
+    }
+
     #[doc(hidden)]
     #[inline(always)]
     fn _into_af1(&mut self) {
@@ -162,7 +292,7 @@ impl<const P: u8, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
 
     #[doc(hidden)]
     #[inline(always)]
-    fn _set_high(&mut self) {
+    fn _set_high(&self) {
         // Safety: Concurrent write access to the GPIO output atomic set register is safe
         let gpio = unsafe { &*gpiox_ptr::<P>() };
         gpio.out_set().write(|w| unsafe { w.bits(1 << N) });
@@ -170,7 +300,7 @@ impl<const P: u8, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
 
     #[doc(hidden)]
     #[inline(always)]
-    fn _set_low(&mut self) {
+    fn _set_low(&self) {
         // Safety: Concurrent write access to the GPIO output atomic clear register is safe
         let gpio = unsafe { &*gpiox_ptr::<P>() };
         gpio.out_clr().write(|w| unsafe { w.bits(1 << N) });
@@ -192,6 +322,14 @@ impl<const P: u8, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
         gpio.out().read().bits() & (1 << N) == 0
     }
 
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _is_output_enabled(&self) -> bool {
+        // Safety: Concurrent read access to the GPIO output enable register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.outen().read().bits() & (1 << N) != 0
+    }
+
     /// Returns [`true`] if the pin is high, [`false`] if the pin is low
     #[inline(always)]
     pub fn is_high(&self) -> bool {
@@ -203,41 +341,202 @@ impl<const P: u8, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
     pub fn is_low(&self) -> bool {
         self._is_low()
     }
+
+    /// Returns [`true`] if the output driver of the pin is currently enabled.
+    #[inline(always)]
+    pub fn is_output_enabled(&self) -> bool {
+        self._is_output_enabled()
+    }
+}
+
+/// Type erasure methods. These are only available for pins with the default
+/// pad mode and drive strength, as the erased pin types do not track them.
+impl<const P: u8, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
+    /// Erases the pin number from the type, keeping the port and mode. This
+    /// allows pins of the same port to be stored together (e.g. in an
+    /// array).
+    #[inline(always)]
+    pub fn downgrade(self) -> PartiallyErasedPin<P, MODE> {
+        PartiallyErasedPin {
+            n: N,
+            _mode: PhantomData,
+        }
+    }
+
+    /// Erases the port and pin number from the type, keeping the mode. This
+    /// allows pins of different ports to be stored together, or selected at
+    /// runtime. The pin can be turned back into a [`Pin`] with
+    /// [`TryFrom`].
+    #[inline(always)]
+    pub fn erase(self) -> ErasedPin<MODE> {
+        ErasedPin {
+            port: P,
+            n: N,
+            _mode: PhantomData,
+        }
+    }
 }
 
 /// Methods for input pins.
-impl<const P: u8, const N: u8> Pin<P, N, Input> {
-    /// Configures the pin as an input/output pin.
+impl<const P: u8, const N: u8, PAD: PadMode> Pin<P, N, Input, Vddio, PAD> {
+    /// Configures the pin as an input/output pin. Any pull resistor of the
+    /// pin is removed.
     #[inline(always)]
-    pub fn into_input_output(self) -> Pin<P, N, InputOutput> {
+    pub fn into_input_output(mut self) -> Pin<P, N, InputOutput> {
+        self._clear_pad();
         // Enable the output for the pin
         let mut pin = Pin::<P, N, InputOutput>::new();
         pin._output_enable();
         pin
     }
 
-    /// Configures the pin as an alternate function 1 pin.
+    /// Configures the pin as an alternate function 1 pin. Any pull resistor
+    /// of the pin is removed.
     #[inline(always)]
-    pub fn into_af1(self) -> Pin<P, N, Af1> {
+    pub fn into_af1(mut self) -> Pin<P, N, Af1> {
+        self._clear_pad();
         let mut pin = Pin::<P, N, Af1>::new();
         pin._into_af1();
         pin
     }
 
-    /// Configures the pin as an alternate function 2 pin.
+    /// Configures the pin as an alternate function 2 pin. Any pull resistor
+    /// of the pin is removed.
     #[inline(always)]
-    pub fn into_af2(self) -> Pin<P, N, Af2> {
+    pub fn into_af2(mut self) -> Pin<P, N, Af2> {
+        self._clear_pad();
         let mut pin = Pin::<P, N, Af2>::new();
         pin._into_af2();
         pin
     }
+
+    /// Removes the pull resistor of the pin, leaving the input floating
+    /// (high impedance).
+    #[inline(always)]
+    pub fn into_floating(mut self) -> Pin<P, N, Input> {
+        self._set_pad::<HighImpedance>();
+        Pin::new()
+    }
+
+    /// Connects the weak pull-up resistor (about 1 MΩ) of the pin.
+    ///
+    /// Example:
+    /// ```
+    /// // Button between p0_2 and ground, reads low while pressed
+    /// let button = pins.p0_2.into_pull_up();
+    /// let pressed = button.is_low();
+    /// ```
+    #[inline(always)]
+    pub fn into_pull_up(mut self) -> Pin<P, N, Input, Vddio, PullUpWeak> {
+        self._set_pad::<PullUpWeak>();
+        Pin::new()
+    }
+
+    /// Connects the strong pull-up resistor (about 25 kΩ) of the pin.
+    #[inline(always)]
+    pub fn into_pull_up_strong(mut self) -> Pin<P, N, Input, Vddio, PullUpStrong> {
+        self._set_pad::<PullUpStrong>();
+        Pin::new()
+    }
+
+    /// Connects the weak pull-down resistor (about 1 MΩ) of the pin.
+    #[inline(always)]
+    pub fn into_pull_down(mut self) -> Pin<P, N, Input, Vddio, PullDownWeak> {
+        self._set_pad::<PullDownWeak>();
+        Pin::new()
+    }
+
+    /// Connects the strong pull-down resistor (about 25 kΩ) of the pin.
+    #[inline(always)]
+    pub fn into_pull_down_strong(mut self) -> Pin<P, N, Input, Vddio, PullDownStrong> {
+        self._set_pad::<PullDownStrong>();
+        Pin::new()
+    }
+
+    /// Configures the pin to raise an interrupt on `edge`, and enables the
+    /// interrupt. Any pending interrupt of the pin is cleared first.
+    ///
+    /// All pins of a port share one interrupt vector (see
+    /// [`Port::interrupt`]): `GPIO0`, `GPIO1` or `GPIO2`. The vector must be
+    /// unmasked in the NVIC, and its handler must clear the flag of the pin
+    /// with [`Pin::clear_interrupt`] (or dispatch with [`handle_irq`], which
+    /// clears it).
+    ///
+    /// Note: With a level trigger ([`InterruptEdge::High`] or
+    /// [`InterruptEdge::Low`]), the interrupt is raised again as long as the
+    /// level persists, so the handler should disable it or remove the cause.
+    ///
+    /// Example:
+    /// ```
+    /// let button = pins.p0_2.into_interrupt(hal::gpio::InterruptEdge::Falling);
+    /// unsafe { cortex_m::peripheral::NVIC::unmask(pins.port.interrupt()) };
+    ///
+    /// #[interrupt]
+    /// fn GPIO0() {
+    ///     // The button must be shared with the handler, e.g. in a Mutex
+    ///     if button.is_interrupt_pending() {
+    ///         button.clear_interrupt();
+    ///         // ...
+    ///     }
+    /// }
+    /// ```
+    pub fn into_interrupt(self, edge: InterruptEdge) -> Self {
+        _set_interrupt(P, N, Some(edge));
+        self
+    }
+
+    /// Disables the interrupt of the pin. The trigger configuration is
+    /// kept.
+    pub fn disable_interrupt(&mut self) {
+        _set_interrupt(P, N, None);
+    }
+
+    /// Returns [`true`] if the interrupt flag of the pin is set.
+    #[inline(always)]
+    pub fn is_interrupt_pending(&self) -> bool {
+        // Safety: Concurrent read access to the GPIO interrupt flag register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.intfl().read().bits() & (1 << N) != 0
+    }
+
+    /// Clears the interrupt flag of the pin.
+    #[inline(always)]
+    pub fn clear_interrupt(&mut self) {
+        // Safety: Interrupt flags are cleared through the atomic clear
+        // register, so only the flag of this pin is affected
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.intfl_clr().write(|w| unsafe { w.bits(1 << N) });
+    }
+
+    /// Clears the pending interrupt flag of the pin, then returns [`true`] if
+    /// the pin is high. This is intended for servicing latched data-ready
+    /// lines.
+    ///
+    /// The flag is cleared before the level is read, so an edge arriving
+    /// during the call is never lost: either it happened before the flag was
+    /// cleared and is reflected in the returned level, or it happened after
+    /// and sets the flag again, raising a new interrupt.
+    #[inline(always)]
+    pub fn read_and_clear(&mut self) -> bool {
+        // Safety: Interrupt flags are cleared through the atomic clear
+        // register, so only the flag of this pin is affected
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.intfl_clr().write(|w| unsafe { w.bits(1 << N) });
+        self._is_high()
+    }
 }
 
 /// Methods for input/output pins.
-impl<const P: u8, const N: u8> Pin<P, N, InputOutput> {
-    /// Configures the pin as an input pin (disables output).
+impl<const P: u8, const N: u8, DRIVE: DriveStrength>
+    Pin<P, N, InputOutput, Vddio, HighImpedance, DRIVE>
+{
+    /// Configures the pin as an input pin (disables output). The drive
+    /// strength of the pin is reset to [`Strength0`].
     #[inline(always)]
-    pub fn into_input(self) -> Pin<P, N, Input> {
+    pub fn into_input(mut self) -> Pin<P, N, Input> {
+        if DRIVE::BITS != 0 {
+            self._set_drive_strength::<Strength0>();
+        }
         // Disable the output for the pin
         let mut pin = Pin::<P, N, Input>::new();
         pin._output_disable();
@@ -245,14 +544,118 @@ impl<const P: u8, const N: u8> Pin<P, N, InputOutput> {
     }
 
     /// Sets the pin high.
+    ///
+    /// This only needs a shared reference: the output is changed through the
+    /// atomic set and clear registers of the port, which only affect the bit
+    /// of this pin. Pins of the same port (or shared references to one pin)
+    /// can therefore be driven from different contexts, such as interrupt
+    /// handlers, without a lock.
     #[inline(always)]
-    pub fn set_high(&mut self) {
+    pub fn set_high(&self) {
         self._set_high();
     }
 
-    /// Sets the pin low.
+    /// Sets the pin low. Like [`Pin::set_high`], this only needs a shared
+    /// reference.
     #[inline(always)]
-    pub fn set_low(&mut self) {
+    pub fn set_low(&self) {
+        self._set_low();
+    }
+
+    /// Toggles the pin between high and low.
+    ///
+    /// The GPIO ports have no output toggle register, so the output level is
+    /// read and then set through the atomic set or clear register. This is
+    /// done in a critical section, so a toggle from another context cannot
+    /// be lost in between. Like [`Pin::set_high`], this only needs a shared
+    /// reference.
+    ///
+    /// Example:
+    /// ```
+    /// let led = pins.p2_0.into_input_output();
+    /// loop {
+    ///     led.toggle();
+    ///     delay.delay_ms(500);
+    /// }
+    /// ```
+    #[inline(always)]
+    pub fn toggle(&self) {
+        // Safety: Only the bit of this pin is written to the atomic set or
+        // clear register
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        interrupt::free(|_| match toggle_write(gpio.out().read().bits(), N) {
+            OutWrite::Set(mask) => {
+                gpio.out_set().write(|w| unsafe { w.bits(mask) });
+            }
+            OutWrite::Clear(mask) => {
+                gpio.out_clr().write(|w| unsafe { w.bits(mask) });
+            }
+        });
+    }
+
+    /// Drives the pin high, enabling the output driver if it was disabled
+    /// with [`Pin::set_hiz`].
+    ///
+    /// Together with [`Pin::set_drive_low`] and [`Pin::set_hiz`], this
+    /// switches between the three states needed for charlieplexing without
+    /// changing the type of the pin. The output level is set before the
+    /// driver is enabled, so the pin never glitches to the old level. Each
+    /// method is at most two writes to the atomic set and clear registers of
+    /// the port, so switching a pin takes a few tens of CPU cycles, which
+    /// fits easily into a multi-kHz refresh rate.
+    ///
+    /// Example:
+    /// ```
+    /// // Light the LED between p0_0 (anode) and p0_1 (cathode) of a
+    /// // charlieplexed array, with p0_2 disconnected
+    /// let a = pins.p0_0.into_input_output();
+    /// let b = pins.p0_1.into_input_output();
+    /// let c = pins.p0_2.into_input_output();
+    /// c.set_hiz();
+    /// a.set_drive_high();
+    /// b.set_drive_low();
+    /// ```
+    #[inline(always)]
+    pub fn set_drive_high(&self) {
+        self._set_high();
+        // Safety: Concurrent write access to the GPIO output enable atomic set register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.outen_set().write(|w| unsafe { w.bits(1 << N) });
+    }
+
+    /// Drives the pin low, enabling the output driver if it was disabled
+    /// with [`Pin::set_hiz`]. See [`Pin::set_drive_high`].
+    #[inline(always)]
+    pub fn set_drive_low(&self) {
+        self._set_low();
+        // Safety: Concurrent write access to the GPIO output enable atomic set register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.outen_set().write(|w| unsafe { w.bits(1 << N) });
+    }
+
+    /// Disables the output driver, leaving the pin in high impedance (or
+    /// pulled, if a pull resistor is configured). The pin can be driven
+    /// again with [`Pin::set_drive_high`] or [`Pin::set_drive_low`]. See
+    /// [`Pin::set_drive_high`].
+    #[inline(always)]
+    pub fn set_hiz(&self) {
+        // Safety: Concurrent write access to the GPIO output enable atomic clear register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.outen_clr().write(|w| unsafe { w.bits(1 << N) });
+    }
+
+    /// Drives the pin high for `us` microseconds, then drives it low. This
+    /// is a blocking operation.
+    ///
+    /// Example:
+    /// ```
+    /// // Blink an activity LED for 50 ms each time a byte is received
+    /// let byte = uart.read_byte();
+    /// led.pulse(&mut delay, 50_000);
+    /// ```
+    pub fn pulse(&mut self, delay: &mut impl DelayNs, us: u32) {
+        self._set_high();
+        delay.delay_us(us);
         self._set_low();
     }
 
@@ -283,15 +686,69 @@ impl<const P: u8, const N: u8> Pin<P, N, InputOutput> {
         gpio.vssel()
             .modify(|r, w| unsafe { w.bits(r.bits() | (1 << N)) });
     }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _set_drive_strength<NEW: DriveStrength>(&mut self) {
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        let (ds1, ds0) = drive_strength_bits::<NEW>();
+        // The drive strength registers have no atomic set and clear
+        // registers, so they are modified in a critical section
+        interrupt::free(|_| {
+            // Safety: Only the bit of this pin is modified
+            gpio.ds0()
+                .modify(|r, w| unsafe { w.bits((r.bits() & !(1 << N)) | (ds0 << N)) });
+            gpio.ds1()
+                .modify(|r, w| unsafe { w.bits((r.bits() & !(1 << N)) | (ds1 << N)) });
+        });
+    }
+
+    /// Sets the pin output drive strength, from [`Strength0`] (weakest) to
+    /// [`Strength3`] (strongest), and returns the pin with the drive
+    /// strength in its type. This allows a driver to require a minimum drive
+    /// strength for a pin.
+    ///
+    /// All drive strengths can be used with either power supply (VDDIO or
+    /// VDDIOH). The resulting output current depends on both the drive
+    /// strength and the supply voltage; refer to the electrical
+    /// characteristics in the datasheet.
+    ///
+    /// The drive strength is selected by the `DS1:DS0` bits of the pin:
+    ///
+    /// | Strength      | DS1 | DS0 |
+    /// |---------------|-----|-----|
+    /// | [`Strength0`] | 0   | 0   |
+    /// | [`Strength1`] | 0   | 1   |
+    /// | [`Strength2`] | 1   | 0   |
+    /// | [`Strength3`] | 1   | 1   |
+    ///
+    /// Example:
+    /// ```
+    /// use hal::gpio::{HighImpedance, InputOutput, Pin, Strength3, Vddio};
+    ///
+    /// let led: Pin<2, 0, InputOutput, Vddio, HighImpedance, Strength3> =
+    ///     pins.p2_0.into_input_output().into_drive_strength();
+    /// ```
+    #[inline(always)]
+    pub fn into_drive_strength<NEW: DriveStrength>(
+        mut self,
+    ) -> Pin<P, N, InputOutput, Vddio, HighImpedance, NEW> {
+        self._set_drive_strength::<NEW>();
+        Pin::new()
+    }
 }
 
 /// embedded-hal ErrorType trait
-impl<const P: u8, const N: u8, MODE: PinMode> ErrorType for Pin<P, N, MODE> {
+impl<const P: u8, const N: u8, MODE: PinMode, PAD: PadMode, DRIVE: DriveStrength> ErrorType
+    for Pin<P, N, MODE, Vddio, PAD, DRIVE>
+{
     type Error = core::convert::Infallible;
 }
 
 /// embedded-hal InputPin trait
-impl<const P: u8, const N: u8, MODE: PinMode> InputPin for Pin<P, N, MODE> {
+impl<const P: u8, const N: u8, MODE: PinMode, PAD: PadMode, DRIVE: DriveStrength> InputPin
+    for Pin<P, N, MODE, Vddio, PAD, DRIVE>
+{
     #[inline(always)]
     fn is_high(&mut self) -> Result<bool, Self::Error> {
         Ok(self._is_high())
@@ -304,7 +761,9 @@ impl<const P: u8, const N: u8, MODE: PinMode> InputPin for Pin<P, N, MODE> {
 }
 
 /// embedded-hal OutputPin trait
-impl<const P: u8, const N: u8> OutputPin for Pin<P, N, InputOutput> {
+impl<const P: u8, const N: u8, DRIVE: DriveStrength> OutputPin
+    for Pin<P, N, InputOutput, Vddio, HighImpedance, DRIVE>
+{
     #[inline(always)]
     fn set_high(&mut self) -> Result<(), Self::Error> {
         self._set_high();
@@ -319,7 +778,9 @@ impl<const P: u8, const N: u8> OutputPin for Pin<P, N, InputOutput> {
 }
 
 /// embedded-hal StatefulOutputPin trait
-impl<const P: u8, const N: u8> StatefulOutputPin for Pin<P, N, InputOutput> {
+impl<const P: u8, const N: u8, DRIVE: DriveStrength> StatefulOutputPin
+    for Pin<P, N, InputOutput, Vddio, HighImpedance, DRIVE>
+{
     #[inline(always)]
     fn is_set_high(&mut self) -> Result<bool, Self::Error> {
         Ok(self._is_set_high())
@@ -329,6 +790,543 @@ impl<const P: u8, const N: u8> StatefulOutputPin for Pin<P, N, InputOutput> {
     fn is_set_low(&mut self) -> Result<bool, Self::Error> {
         Ok(self._is_set_low())
     }
+
+    #[inline(always)]
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        Pin::toggle(self);
+        Ok(())
+    }
+}
+
+/// GPIO pin with the port number in its type and the pin number stored at
+/// runtime. Created with [`Pin::downgrade`].
+///
+/// Register accesses are still specialized for the port, so this is cheaper
+/// than erasing the port as well.
+///
+/// - `P` is the GPIO port number (e.g. `0` for `Gpio0`, `1` for `Gpio1`, etc.)
+/// - `MODE` is one of the pin modes (e.g. `Input`, `InputOutput`, `Af1`, `Af2`).
+///
+/// Example:
+/// ```
+/// let pins = hal::gpio::Gpio2::new(p.gpio2, &mut gcr.reg).split();
+/// let mut leds = [
+///     pins.p2_0.into_input_output().downgrade(),
+///     pins.p2_1.into_input_output().downgrade(),
+///     pins.p2_2.into_input_output().downgrade(),
+/// ];
+/// for led in leds.iter_mut() {
+///     led.set_high();
+/// }
+/// ```
+pub struct PartiallyErasedPin<const P: u8, MODE: PinMode = Input> {
+    n: u8,
+    _mode: PhantomData<MODE>,
+}
+
+/// Methods for partially erased pins in all modes.
+impl<const P: u8, MODE: PinMode> PartiallyErasedPin<P, MODE> {
+    /// Returns the pin number within the port.
+    #[inline(always)]
+    pub fn pin_number(&self) -> u8 {
+        self.n
+    }
+
+    /// Erases the port number from the type as well. See [`Pin::erase`].
+    #[inline(always)]
+    pub fn erase(self) -> ErasedPin<MODE> {
+        ErasedPin {
+            port: P,
+            n: self.n,
+            _mode: PhantomData,
+        }
+    }
+
+    /// Returns [`true`] if the pin is high, [`false`] if the pin is low
+    #[inline(always)]
+    pub fn is_high(&self) -> bool {
+        // Safety: Concurrent read access to the GPIO input register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.in_().read().gpio_in().bits() & (1 << self.n) != 0
+    }
+
+    /// Returns [`true`] if the pin is low, [`false`] if the pin is high
+    #[inline(always)]
+    pub fn is_low(&self) -> bool {
+        !self.is_high()
+    }
+}
+
+/// Methods for partially erased input/output pins.
+impl<const P: u8> PartiallyErasedPin<P, InputOutput> {
+    /// Sets the pin high. Like [`Pin::set_high`], this only needs a shared
+    /// reference.
+    #[inline(always)]
+    pub fn set_high(&self) {
+        // Safety: Concurrent write access to the GPIO output atomic set register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.out_set().write(|w| unsafe { w.bits(1 << self.n) });
+    }
+
+    /// Sets the pin low. Like [`Pin::set_high`], this only needs a shared
+    /// reference.
+    #[inline(always)]
+    pub fn set_low(&self) {
+        // Safety: Concurrent write access to the GPIO output atomic clear register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.out_clr().write(|w| unsafe { w.bits(1 << self.n) });
+    }
+
+    /// Returns [`true`] if the pin is set to high, [`false`] if the pin is set to low.
+    #[inline(always)]
+    pub fn is_set_high(&self) -> bool {
+        // Safety: Concurrent read access to the GPIO output register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.out().read().bits() & (1 << self.n) != 0
+    }
+
+    /// Returns [`true`] if the pin is set to low, [`false`] if the pin is set to high.
+    #[inline(always)]
+    pub fn is_set_low(&self) -> bool {
+        !self.is_set_high()
+    }
+}
+
+/// embedded-hal ErrorType trait
+impl<const P: u8, MODE: PinMode> ErrorType for PartiallyErasedPin<P, MODE> {
+    type Error = core::convert::Infallible;
+}
+
+/// embedded-hal InputPin trait
+impl<const P: u8, MODE: PinMode> InputPin for PartiallyErasedPin<P, MODE> {
+    #[inline(always)]
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(PartiallyErasedPin::is_high(self))
+    }
+
+    #[inline(always)]
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(PartiallyErasedPin::is_low(self))
+    }
+}
+
+/// embedded-hal OutputPin trait
+impl<const P: u8> OutputPin for PartiallyErasedPin<P, InputOutput> {
+    #[inline(always)]
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        PartiallyErasedPin::set_high(self);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        PartiallyErasedPin::set_low(self);
+        Ok(())
+    }
+}
+
+/// embedded-hal StatefulOutputPin trait
+impl<const P: u8> StatefulOutputPin for PartiallyErasedPin<P, InputOutput> {
+    #[inline(always)]
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(PartiallyErasedPin::is_set_high(self))
+    }
+
+    #[inline(always)]
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(PartiallyErasedPin::is_set_low(self))
+    }
+}
+
+/// Restores the pin number of a partially erased pin. The pin is returned
+/// as the error if its number is not `N`.
+impl<const P: u8, const N: u8, MODE: PinMode> TryFrom<PartiallyErasedPin<P, MODE>>
+    for Pin<P, N, MODE>
+{
+    type Error = PartiallyErasedPin<P, MODE>;
+
+    #[inline(always)]
+    fn try_from(pin: PartiallyErasedPin<P, MODE>) -> Result<Self, Self::Error> {
+        if pin.n == N {
+            Ok(Pin::new())
+        } else {
+            Err(pin)
+        }
+    }
+}
+
+/// Type-erased GPIO pin, with the port and pin number stored at runtime.
+///
+/// Obtained with [`Pin::erase`] or [`PartiallyErasedPin::erase`]. Unlike
+/// [`PartiallyErasedPin`], pins of different ports can be stored together,
+/// at the cost of selecting the port registers at runtime. The pin can be
+/// turned back into a [`Pin`] with [`TryFrom`], which fails (returning the
+/// erased pin) if the port or pin number does not match.
+///
+/// - `MODE` is one of the pin modes (e.g. `Input`, `InputOutput`, `Af1`, `Af2`).
+///
+/// Example:
+/// ```
+/// use hal::gpio::{ErasedPin, InputOutput, Pin};
+///
+/// let mut leds: [ErasedPin<InputOutput>; 2] = [
+///     pins0.p0_2.into_input_output().erase(),
+///     pins2.p2_0.into_input_output().erase(),
+/// ];
+/// for led in leds.iter_mut() {
+///     led.set_high();
+/// }
+/// // Turn the first LED back into a typed pin
+/// let [led0, _] = leds;
+/// if let Ok(led0) = Pin::<0, 2, InputOutput>::try_from(led0) {
+///     led0.set_low();
+/// }
+/// ```
+pub struct ErasedPin<MODE: PinMode = Input> {
+    port: u8,
+    n: u8,
+    _mode: PhantomData<MODE>,
+}
+
+/// Methods for erased pins in all modes.
+impl<MODE: PinMode> ErasedPin<MODE> {
+    /// Returns the GPIO port number of the pin.
+    #[inline(always)]
+    pub fn port_number(&self) -> u8 {
+        self.port
+    }
+
+    /// Returns the pin number within the port.
+    #[inline(always)]
+    pub fn pin_number(&self) -> u8 {
+        self.n
+    }
+
+    /// Returns [`true`] if the pin is high, [`false`] if the pin is low
+    #[inline(always)]
+    pub fn is_high(&self) -> bool {
+        // Safety: Concurrent read access to the GPIO input register is safe
+        let gpio = unsafe { &*gpio_port_ptr(self.port) };
+        gpio.in_().read().gpio_in().bits() & (1 << self.n) != 0
+    }
+
+    /// Returns [`true`] if the pin is low, [`false`] if the pin is high
+    #[inline(always)]
+    pub fn is_low(&self) -> bool {
+        !self.is_high()
+    }
+}
+
+/// Methods for erased input/output pins.
+impl ErasedPin<InputOutput> {
+    /// Sets the pin high. Like [`Pin::set_high`], this only needs a shared
+    /// reference.
+    #[inline(always)]
+    pub fn set_high(&self) {
+        // Safety: Concurrent write access to the GPIO output atomic set register is safe
+        let gpio = unsafe { &*gpio_port_ptr(self.port) };
+        gpio.out_set().write(|w| unsafe { w.bits(1 << self.n) });
+    }
+
+    /// Sets the pin low. Like [`Pin::set_high`], this only needs a shared
+    /// reference.
+    #[inline(always)]
+    pub fn set_low(&self) {
+        // Safety: Concurrent write access to the GPIO output atomic clear register is safe
+        let gpio = unsafe { &*gpio_port_ptr(self.port) };
+        gpio.out_clr().write(|w| unsafe { w.bits(1 << self.n) });
+    }
+
+    /// Returns [`true`] if the pin is set to high, [`false`] if the pin is set to low.
+    #[inline(always)]
+    pub fn is_set_high(&self) -> bool {
+        // Safety: Concurrent read access to the GPIO output register is safe
+        let gpio = unsafe { &*gpio_port_ptr(self.port) };
+        gpio.out().read().bits() & (1 << self.n) != 0
+    }
+
+    /// Returns [`true`] if the pin is set to low, [`false`] if the pin is set to high.
+    #[inline(always)]
+    pub fn is_set_low(&self) -> bool {
+        !self.is_set_high()
+    }
+}
+
+/// embedded-hal ErrorType trait
+impl<MODE: PinMode> ErrorType for ErasedPin<MODE> {
+    type Error = core::convert::Infallible;
+}
+
+/// embedded-hal InputPin trait
+impl<MODE: PinMode> InputPin for ErasedPin<MODE> {
+    #[inline(always)]
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(ErasedPin::is_high(self))
+    }
+
+    #[inline(always)]
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(ErasedPin::is_low(self))
+    }
+}
+
+/// embedded-hal OutputPin trait
+impl OutputPin for ErasedPin<InputOutput> {
+    #[inline(always)]
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        ErasedPin::set_high(self);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        ErasedPin::set_low(self);
+        Ok(())
+    }
+}
+
+/// embedded-hal StatefulOutputPin trait
+impl StatefulOutputPin for ErasedPin<InputOutput> {
+    #[inline(always)]
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(ErasedPin::is_set_high(self))
+    }
+
+    #[inline(always)]
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(ErasedPin::is_set_low(self))
+    }
+}
+
+/// Restores the port and pin number of an erased pin. The pin is returned
+/// as the error if its port or pin number does not match.
+impl<const P: u8, const N: u8, MODE: PinMode> TryFrom<ErasedPin<MODE>> for Pin<P, N, MODE> {
+    type Error = ErasedPin<MODE>;
+
+    #[inline(always)]
+    fn try_from(pin: ErasedPin<MODE>) -> Result<Self, Self::Error> {
+        if pin.port == P && pin.n == N {
+            Ok(Pin::new())
+        } else {
+            Err(pin)
+        }
+    }
+}
+
+/// Handle to the port-wide configuration of a GPIO port.
+///
+/// - `P` is the GPIO port number (e.g. `0` for `Gpio0`, `1` for `Gpio1`, etc.)
+///
+/// A port handle is obtained from the `port` field of the pins returned by
+/// `split`. Only one handle exists for each port.
+pub struct Port<const P: u8> {
+    _private: (),
+}
+
+/// Snapshot of the configuration of every pin on a GPIO port.
+///
+/// The following registers are captured:
+/// - `EN0`, `EN1`, `EN2`: Pin function (GPIO or alternate function)
+/// - `OUTEN`: Output enable
+/// - `OUT`: Output value
+/// - `INEN`: Input enable
+/// - `PADCTRL0`, `PADCTRL1`, `PS`: Pad mode (pull-up/pull-down)
+/// - `VSSEL`: Power supply
+/// - `DS0`, `DS1`: Drive strength
+/// - `HYSEN`, `SRSEL`: Hysteresis and slew rate
+///
+/// Interrupt and wakeup configuration is not captured.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PortConfig {
+    en0: u32,
+    en1: u32,
+    en2: u32,
+    outen: u32,
+    out: u32,
+    inen: u32,
+    padctrl0: u32,
+    padctrl1: u32,
+    ps: u32,
+    vssel: u32,
+    ds0: u32,
+    ds1: u32,
+    hysen: u32,
+    srsel: u32,
+}
+
+impl<const P: u8> Port<P> {
+    const fn new() -> Self {
+        Self { _private: () }
+    }
+
+    /// Returns the interrupt vector of the GPIO port, e.g. for unmasking it
+    /// in the NVIC.
+    pub const fn interrupt(&self) -> crate::Interrupt {
+        match P {
+            0 => crate::Interrupt::GPIO0,
+            1 => crate::Interrupt::GPIO1,
+            2 => crate::Interrupt::GPIO2,
+            _ => panic!("Invalid GPIO port number"),
+        }
+    }
+
+    /// Returns the input level of all 32 pins of the port, with pin `n` in
+    /// bit `n`.
+    ///
+    /// Example:
+    /// ```
+    /// // Read an 8-bit parallel bus on p0_0 to p0_7
+    /// let byte = pins.port.read_all() as u8;
+    /// ```
+    #[inline(always)]
+    pub fn read_all(&self) -> u32 {
+        // Safety: Concurrent read access to the GPIO input register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.in_().read().gpio_in().bits()
+    }
+
+    /// Sets the output value of all 32 pins of the port at once, with pin
+    /// `n` in bit `n`. Only pins with the output enabled (such as
+    /// input/output pins) are driven.
+    ///
+    /// Note: This overwrites the output value of every pin of the port,
+    /// including pins owned elsewhere in the program. Use
+    /// [`Port::set_mask`] and [`Port::clear_mask`] to change only some pins.
+    #[inline(always)]
+    pub fn write_all(&mut self, value: u32) {
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.out().write(|w| unsafe { w.bits(value) });
+    }
+
+    /// Sets the pins in `mask` high in a single write, leaving the other
+    /// pins of the port unchanged.
+    ///
+    /// Example:
+    /// ```
+    /// // Drive an 8-bit parallel bus on p0_0 to p0_7 with `byte`
+    /// pins.port.clear_mask(!(byte as u32) & 0xFF);
+    /// pins.port.set_mask(byte as u32);
+    /// ```
+    #[inline(always)]
+    pub fn set_mask(&self, mask: u32) {
+        // Safety: Concurrent write access to the GPIO output atomic set register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.out_set().write(|w| unsafe { w.bits(mask) });
+    }
+
+    /// Sets the pins in `mask` low in a single write, leaving the other
+    /// pins of the port unchanged.
+    #[inline(always)]
+    pub fn clear_mask(&self, mask: u32) {
+        // Safety: Concurrent write access to the GPIO output atomic clear register is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        gpio.out_clr().write(|w| unsafe { w.bits(mask) });
+    }
+
+    /// Captures the configuration of every pin on the port. See
+    /// [`PortConfig`] for the registers that are captured.
+    ///
+    /// Example:
+    /// ```
+    /// let mut pins = hal::gpio::Gpio0::new(p.gpio0, &mut gcr.reg).split();
+    /// let config = pins.port.snapshot();
+    /// // ... enter and wake up from a low power mode ...
+    /// // Safety: No pin of port 0 was reconfigured since the snapshot
+    /// unsafe { pins.port.restore(&config) };
+    /// ```
+    pub fn snapshot(&self) -> PortConfig {
+        // Safety: Concurrent read access to the GPIO configuration registers is safe
+        let gpio = unsafe { &*gpiox_ptr::<P>() };
+        PortConfig {
+            en0: gpio.en0().read().bits(),
+            en1: gpio.en1().read().bits(),
+            en2: gpio.en2().read().bits(),
+            outen: gpio.outen().read().bits(),
+            out: gpio.out().read().bits(),
+            inen: gpio.inen().read().bits(),
+            padctrl0: gpio.padctrl0().read().bits(),
+            padctrl1: gpio.padctrl1().read().bits(),
+            ps: gpio.ps().read().bits(),
+            vssel: gpio.vssel().read().bits(),
+            ds0: gpio.ds0().read().bits(),
+            ds1: gpio.ds1().read().bits(),
+            hysen: gpio.hysen().read().bits(),
+            srsel: gpio.srsel().read().bits(),
+        }
+    }
+
+    /// Restores the configuration of every pin on the port from a snapshot
+    /// taken with [`Port::snapshot`].
+    ///
+    /// The output value and pad configuration are restored before the
+    /// output enable and pin function, so pins do not glitch while they are
+    /// being restored.
+    ///
+    /// ## Safety
+    /// No pin of the port may have been reconfigured since the snapshot was
+    /// taken. Otherwise, the restored configuration of a pin may not match
+    /// the type of the [`Pin`] that owns it.
+    pub unsafe fn restore(&mut self, config: &PortConfig) {
+        let gpio = &*gpiox_ptr::<P>();
+        gpio.out().write(|w| w.bits(config.out));
+        gpio.vssel().write(|w| w.bits(config.vssel));
+        gpio.padctrl0().write(|w| w.bits(config.padctrl0));
+        gpio.padctrl1().write(|w| w.bits(config.padctrl1));
+        gpio.ps().write(|w| w.bits(config.ps));
+        gpio.ds0().write(|w| w.bits(config.ds0));
+        gpio.ds1().write(|w| w.bits(config.ds1));
+        gpio.hysen().write(|w| w.bits(config.hysen));
+        gpio.srsel().write(|w| w.bits(config.srsel));
+        gpio.inen().write(|w| w.bits(config.inen));
+        gpio.outen().write(|w| w.bits(config.outen));
+        gpio.en2().write(|w| w.bits(config.en2));
+        gpio.en1().write(|w| w.bits(config.en1));
+        gpio.en0().write(|w| w.bits(config.en0));
+    }
+}
+
+/// Configures a set of pins from a declarative table, returning a tuple of
+/// the configured pins in the order they are listed. Each pin keeps its
+/// distinct type, exactly as if the transitions were called one at a time.
+///
+/// Supported roles:
+/// - `input`: Leaves the pin as an input pin
+/// - `output`: Calls `into_input_output()`
+/// - `af1`: Calls `into_af1()`
+/// - `af2`: Calls `into_af2()`
+///
+/// Example:
+/// ```
+/// let pins0 = hal::gpio::Gpio0::new(p.gpio0, &mut gcr.reg).split();
+/// let pins2 = hal::gpio::Gpio2::new(p.gpio2, &mut gcr.reg).split();
+/// let (led_red, led_green, led_blue) = hal::configure_pins!(pins2, {
+///     p2_0: output,
+///     p2_1: output,
+///     p2_2: output,
+/// });
+/// let (rx, tx, button) = hal::configure_pins!(pins0, {
+///     p0_0: af1,
+///     p0_1: af1,
+///     p0_2: input,
+/// });
+/// ```
+#[macro_export]
+macro_rules! configure_pins {
+    ($parts:ident, { $($pin:ident : $role:ident),* $(,)? }) => {
+        ( $( $crate::configure_pins!(@role $parts.$pin, $role), )* )
+    };
+    (@role $pin:expr, input) => {
+        $pin
+    };
+    (@role $pin:expr, output) => {
+        $pin.into_input_output()
+    };
+    (@role $pin:expr, af1) => {
+        $pin.into_af1()
+    };
+    (@role $pin:expr, af2) => {
+        $pin.into_af2()
+    };
 }
 
 /// Macro that generates a GPIO module with an interface for splitting GPIO pins.
@@ -344,6 +1342,8 @@ macro_rules! gpio {
             pub mod $MODULE_HAL {
                 /// Collection of GPIO pins from a single GPIO port.
                 pub struct Parts {
+                    /// Handle to the port-wide configuration.
+                    pub port: super::Port<$PORT_NUM>,
                     $(
                         pub [<p $PORT_NUM _ $PIN_NUM>]: [<P $PORT_NUM _ $PIN_NUM>],
                     )+
@@ -395,6 +1395,7 @@ macro_rules! gpio {
                     /// Splits the GPIO peripheral into independent pins.
                     pub fn split(self) -> Parts {
                         Parts {
+                            port: super::Port::new(),
                             $(
                                 [<p $PORT_NUM _ $PIN_NUM>]: [<P $PORT_NUM _ $PIN_NUM>]::new(),
                             )+
@@ -402,10 +1403,10 @@ macro_rules! gpio {
                     }
                 }
 
-                // #[doc="Common type for "]
-                // #[doc=stringify!($GPIOX)]
-                // #[doc=" related pins"]
-                // pub type $PX_x<MODE> = super::PartiallyErasedPin<$port_id, MODE>;
+                #[doc="Common type for "]
+                #[doc=stringify!([<$MODULE_PAC:upper>])]
+                #[doc=" related pins"]
+                pub type [<P $PORT_NUM x>]<MODE = super::Input> = super::PartiallyErasedPin<$PORT_NUM, MODE>;
 
                 // Creates a zero-sized type for each pin
                 $(
@@ -444,3 +1445,284 @@ const fn gpiox_ptr<const P: u8>() -> *const crate::pac::gpio0::RegisterBlock {
         _ => panic!("Invalid GPIO port number"),
     }
 }
+
+/// Function to get the address of a GPIO peripheral from a runtime port number.
+#[inline(always)]
+fn gpio_port_ptr(port: u8) -> *const crate::pac::gpio0::RegisterBlock {
+    match port {
+        0 => crate::pac::Gpio0::ptr(),
+        1 => crate::pac::Gpio1::ptr(),
+        2 => crate::pac::Gpio2::ptr(),
+        _ => panic!("Invalid GPIO port number"),
+    }
+}
+
+/// Number of GPIO ports with interrupt handler slots.
+const HANDLER_PORTS: usize = 3;
+/// Number of interrupt handler slots per GPIO port.
+const HANDLER_PINS: usize = 32;
+
+/// GPIO interrupt handlers, indexed by port and pin number.
+type HandlerTable = [[Option<fn()>; HANDLER_PINS]; HANDLER_PORTS];
+
+/// Registered GPIO interrupt handlers.
+static GPIO_HANDLERS: Mutex<RefCell<HandlerTable>> =
+    Mutex::new(RefCell::new([[None; HANDLER_PINS]; HANDLER_PORTS]));
+
+/// Registers `handler` to be called by [`handle_irq`] when the interrupt of
+/// pin `pin` on port `port` is pending. Any previously registered handler
+/// for the pin is replaced.
+///
+/// Handlers are plain function pointers, so they cannot capture any state
+/// and are always `'static`. They are called from interrupt context, so any
+/// state they share with the rest of the program must be protected (e.g.
+/// with a [`cortex_m::interrupt::Mutex`]). Registration is done in a
+/// critical section, so it is safe to call while GPIO interrupts are
+/// enabled.
+///
+/// Registering a handler does not enable the interrupt of the pin.
+///
+/// ## Panics
+/// Panics if `port` or `pin` is not a valid GPIO port or pin number.
+///
+/// Example:
+/// ```
+/// fn on_button() {
+///     // ...
+/// }
+///
+/// hal::gpio::register_handler(0, 2, on_button);
+///
+/// #[interrupt]
+/// fn GPIO0() {
+///     hal::gpio::handle_irq(0);
+/// }
+/// ```
+pub fn register_handler(port: u8, pin: u8, handler: fn()) {
+    _set_handler(port, pin, Some(handler));
+}
+
+/// Removes the handler registered for pin `pin` on port `port`.
+///
+/// ## Panics
+/// Panics if `port` or `pin` is not a valid GPIO port or pin number.
+pub fn unregister_handler(port: u8, pin: u8) {
+    _set_handler(port, pin, None);
+}
+
+#[doc(hidden)]
+#[inline(always)]
+fn _set_handler(port: u8, pin: u8, handler: Option<fn()>) {
+    assert!((port as usize) < HANDLER_PORTS, "Invalid GPIO port number");
+    assert!((pin as usize) < HANDLER_PINS, "Invalid GPIO pin number");
+    interrupt::free(|cs| {
+        GPIO_HANDLERS.borrow(cs).borrow_mut()[port as usize][pin as usize] = handler;
+    });
+}
+
+/// Services the interrupt of GPIO port `port`. This should be called from
+/// the `GPIOn` interrupt handler of the port.
+///
+/// The flags of all pending and enabled pin interrupts are cleared, then the
+/// registered handler of each of these pins is called, in order of pin
+/// number. Pending interrupts without a registered handler are cleared
+/// without further action.
+///
+/// Using this function is optional: interrupts can also be serviced
+/// directly through the GPIO registers.
+///
+/// ## Panics
+/// Panics if `port` is not a valid GPIO port number.
+pub fn handle_irq(port: u8) {
+    // Safety: Interrupt flags are cleared through the atomic clear register,
+    // so only the serviced flags are affected
+    let gpio = unsafe { &*gpio_port_ptr(port) };
+    let pending = gpio.intfl().read().bits() & gpio.inten().read().bits();
+    gpio.intfl_clr().write(|w| unsafe { w.bits(pending) });
+    for pin in 0..HANDLER_PINS {
+        if pending & (1 << pin) == 0 {
+            continue;
+        }
+        let handler = interrupt::free(|cs| GPIO_HANDLERS.borrow(cs).borrow()[port as usize][pin]);
+        if let Some(handler) = handler {
+            handler();
+        }
+    }
+}
+
+/// Change in position for each quadrature transition, indexed by
+/// `(old_state << 2) | new_state` where a state is `(A << 1) | B`. Invalid
+/// transitions (both inputs changing at once) and non-transitions are `0`.
+const QUADRATURE_STEPS: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
+
+/// Trigger condition of a GPIO pin interrupt, see [`Pin::into_interrupt`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InterruptEdge {
+    /// Rising edge (low to high).
+    Rising,
+    /// Falling edge (high to low).
+    Falling,
+    /// Both rising and falling edges.
+    Both,
+    /// High level.
+    High,
+    /// Low level.
+    Low,
+}
+
+/// # Quadrature Encoder
+///
+/// Decodes a quadrature (rotary) encoder connected to two input pins into a
+/// position counter, using interrupts on both edges of both pins. The
+/// position increases when input A leads input B, and decreases when B
+/// leads A. Each full cycle of the inputs counts four steps.
+///
+/// Illegal transitions, where both inputs appear to change at once (e.g. due
+/// to contact bounce), are ignored.
+///
+/// The GPIO interrupts of both pins must be unmasked in the NVIC, and their
+/// handlers must call [`Encoder::on_interrupt`]. If the pins are on
+/// different ports, this applies to the handlers of both ports.
+///
+/// Example:
+/// ```
+/// let pins = hal::gpio::Gpio0::new(p.gpio0, &mut gcr.reg).split();
+/// let encoder = hal::gpio::Encoder::new(pins.p0_2, pins.p0_3);
+/// unsafe { cortex_m::peripheral::NVIC::unmask(pins.port.interrupt()) };
+/// // Share the encoder with the interrupt handler, e.g. in a Mutex
+///
+/// #[interrupt]
+/// fn GPIO0() {
+///     encoder.on_interrupt();
+/// }
+///
+/// let position = encoder.position();
+/// ```
+pub struct Encoder<const PA: u8, const NA: u8, const PB: u8, const NB: u8> {
+    _pin_a: Pin<PA, NA, Input>,
+    _pin_b: Pin<PB, NB, Input>,
+    state: u8,
+    position: i32,
+}
+
+impl<const PA: u8, const NA: u8, const PB: u8, const NB: u8> Encoder<PA, NA, PB, NB> {
+    /// Construct a new encoder from input pins A and B, and enable
+    /// interrupts on both edges of both pins. The position starts at `0`.
+    pub fn new(pin_a: Pin<PA, NA, Input>, pin_b: Pin<PB, NB, Input>) -> Self {
+        _set_interrupt(PA, NA, Some(InterruptEdge::Both));
+        _set_interrupt(PB, NB, Some(InterruptEdge::Both));
+        let mut encoder = Self {
+            _pin_a: pin_a,
+            _pin_b: pin_b,
+            state: 0,
+            position: 0,
+        };
+        encoder.state = encoder._read_state();
+        encoder
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _read_state(&self) -> u8 {
+        ((self._pin_a._is_high() as u8) << 1) | self._pin_b._is_high() as u8
+    }
+
+    /// Handle a GPIO interrupt by clearing the interrupt flags of both pins
+    /// and updating the position. This must be called from the interrupt
+    /// handler of the port(s) of both pins.
+    pub fn on_interrupt(&mut self) {
+        // The flags are cleared before the pins are read, so an edge arriving
+        // in between raises a new interrupt instead of being lost
+        // Safety: Interrupt flags are cleared through the atomic clear
+        // register, so only the flags of these pins are affected
+        unsafe {
+            (*gpiox_ptr::<PA>()).intfl_clr().write(|w| w.bits(1 << NA));
+            (*gpiox_ptr::<PB>()).intfl_clr().write(|w| w.bits(1 << NB));
+        }
+        let state = self._read_state();
+        let step = QUADRATURE_STEPS[((self.state << 2) | state) as usize];
+        self.position = self.position.wrapping_add(step as i32);
+        self.state = state;
+    }
+
+    /// Returns the current position, in steps.
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// Set the current position, e.g. to `0` to mark a reference point.
+    pub fn set_position(&mut self, position: i32) {
+        self.position = position;
+    }
+
+    /// Disable the pin interrupts and release the pins.
+    pub fn free(self) -> (Pin<PA, NA, Input>, Pin<PB, NB, Input>) {
+        _set_interrupt(PA, NA, None);
+        _set_interrupt(PB, NB, None);
+        (self._pin_a, self._pin_b)
+    }
+}
+
+/// Configure and enable the interrupt of a pin, or disable it if `trigger`
+/// is [`None`].
+#[doc(hidden)]
+#[inline(always)]
+fn _set_interrupt(port: u8, pin: u8, trigger: Option<InterruptEdge>) {
+    let gpio = unsafe { &*gpio_port_ptr(port) };
+    let mask = 1 << pin;
+    let Some(trigger) = trigger else {
+        gpio.inten_clr().write(|w| unsafe { w.bits(mask) });
+        return;
+    };
+    let (edge, rising, dual) = match trigger {
+        InterruptEdge::Rising => (true, true, false),
+        InterruptEdge::Falling => (true, false, false),
+        InterruptEdge::Both => (true, false, true),
+        InterruptEdge::High => (false, true, false),
+        InterruptEdge::Low => (false, false, false),
+    };
+    let set = |bits: u32, value: bool| if value { bits | mask } else { bits & !mask };
+    // The interrupt mode registers have no atomic set and clear registers, so
+    // they are modified in a critical section
+    interrupt::free(|_| {
+        // Safety: Only the bit of this pin is modified
+        gpio.intmode()
+            .modify(|r, w| unsafe { w.bits(set(r.bits(), edge)) });
+        gpio.intpol()
+            .modify(|r, w| unsafe { w.bits(set(r.bits(), rising)) });
+        gpio.dualedge()
+            .modify(|r, w| unsafe { w.bits(set(r.bits(), dual)) });
+    });
+    gpio.intfl_clr().write(|w| unsafe { w.bits(mask) });
+    gpio.inten_set().write(|w| unsafe { w.bits(mask) });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drive_strength_bit_pattern() {
+        let table = [
+            (drive_strength_bits::<Strength0>(), (0, 0)),
+            (drive_strength_bits::<Strength1>(), (0, 1)),
+            (drive_strength_bits::<Strength2>(), (1, 0)),
+            (drive_strength_bits::<Strength3>(), (1, 1)),
+        ];
+        for (bits, expected) in table {
+            assert_eq!(bits, expected);
+        }
+    }
+
+    #[test]
+    fn toggle_register_and_bit() {
+        // A low pin is set, a high pin is cleared, through the bit of the pin
+        assert_eq!(toggle_write(0, 0), OutWrite::Set(1));
+        assert_eq!(toggle_write(1, 0), OutWrite::Clear(1));
+        assert_eq!(toggle_write(0, 31), OutWrite::Set(1 << 31));
+        assert_eq!(toggle_write(1 << 31, 31), OutWrite::Clear(1 << 31));
+        // The other pins of the port do not matter
+        assert_eq!(toggle_write(!(1 << 5), 5), OutWrite::Set(1 << 5));
+        assert_eq!(toggle_write(1 << 5, 4), OutWrite::Set(1 << 4));
+    }
+}
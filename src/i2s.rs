@@ -0,0 +1,433 @@
+//! # Inter-IC Sound (I2S)
+//!
+//! The I2S peripheral streams audio samples to and from external codecs,
+//! DACs, and microphones.
+use crate::gcr::{
+    clocks::{Clock, PeripheralClock},
+    ClockForPeripheral,
+};
+use crate::gpio::{Af1, Pin};
+
+/// Depth (in 32-bit words) of the TX and RX FIFOs.
+pub const FIFO_DEPTH: u8 = 8;
+
+/// Size of each sample written to or read from the FIFO.
+pub enum WordSize {
+    /// 8-bit samples (four samples per FIFO word).
+    Eight,
+    /// 16-bit samples (two samples per FIFO word).
+    Sixteen,
+    /// 32-bit samples (one sample per FIFO word).
+    ThirtyTwo,
+}
+
+/// Channel mode of the I2S frame.
+pub enum ChannelMode {
+    /// Left and right channels.
+    Stereo,
+    /// Left channel only.
+    MonoLeft,
+    /// Right channel only.
+    MonoRight,
+}
+
+/// Error returned by [`I2sPeripheral::try_build`] when a sample rate cannot
+/// be generated from the peripheral clock.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SampleRateError {
+    /// The sample rate is zero, or needs a bit clock faster than PCLK / 2.
+    TooFast,
+    /// The sample rate needs a bit clock divisor larger than the 16-bit
+    /// `clkdiv` field.
+    TooSlow,
+}
+
+/// Computes the `clkdiv` field for a sample rate of `bits`-bit samples,
+/// rounded down to the closest divisor (so the achieved rate is never lower
+/// than requested).
+#[inline]
+const fn compute_clkdiv(
+    pclk_freq: u32,
+    bits: u32,
+    sample_rate: u32,
+) -> Result<u16, SampleRateError> {
+    let divisor = match (4 * bits).checked_mul(sample_rate) {
+        Some(divisor) if divisor != 0 => divisor,
+        _ => return Err(SampleRateError::TooFast),
+    };
+    let div = pclk_freq / divisor;
+    if div == 0 {
+        return Err(SampleRateError::TooFast);
+    }
+    if div - 1 > u16::MAX as u32 {
+        return Err(SampleRateError::TooSlow);
+    }
+    Ok((div - 1) as u16)
+}
+
+/// I2S pins (SCK, WS, SDI, SDO).
+pub type I2sPins = (
+    Pin<1, 2, Af1>,
+    Pin<1, 3, Af1>,
+    Pin<1, 4, Af1>,
+    Pin<1, 5, Af1>,
+);
+
+/// # Inter-IC Sound (I2S) Peripheral
+///
+/// The I2S peripheral uses the following pins on alternate function 1:
+/// - P1.2: Bit clock (SCK)
+/// - P1.3: Frame/word select clock (WS)
+/// - P1.4: Serial data in (SDI)
+/// - P1.5: Serial data out (SDO)
+///
+/// ## Clocking
+/// The bit clock is derived from the peripheral clock (PCLK):
+///
+/// `SCK = PCLK / (2 * (clkdiv + 1))` and `sample rate = SCK / (2 * bits)`
+///
+/// where `bits` is the number of bits per sample. Since `clkdiv` is an
+/// integer, only sample rates of the form `PCLK / (4 * bits * n)` can be
+/// generated exactly. For example, with a 50 MHz PCLK and 16-bit samples,
+/// requesting 48 kHz results in ~48.83 kHz. Use
+/// [`BuiltI2sPeripheral::sample_rate`] to read back the achieved rate.
+///
+/// ## FIFO Behavior
+/// The TX and RX FIFOs are [`FIFO_DEPTH`] 32-bit words deep. Samples smaller
+/// than 32 bits are packed into each word, so a FIFO word holds two 16-bit
+/// samples (one stereo frame) or four 8-bit samples. If the RX FIFO is not
+/// drained in time, an overrun is flagged (see
+/// [`BuiltI2sPeripheral::take_rx_overrun`]).
+///
+/// ## DMA
+/// Streaming is blocking only ([`BuiltI2sPeripheral::write_words`] and
+/// [`BuiltI2sPeripheral::read_words`]). The HAL has no DMA driver yet, so
+/// there is no API that streams a buffer through DMA. The I2S side of a
+/// DMA transfer is set up with [`BuiltI2sPeripheral::enable_tx_dma`] and
+/// [`BuiltI2sPeripheral::enable_rx_dma`], and the DMA channel (addresses,
+/// count and request select) has to be programmed through the PAC `Dma`
+/// peripheral, using [`BuiltI2sPeripheral::fifo_address`] as the FIFO
+/// address.
+///
+/// ## Example
+/// ```
+/// let pins = hal::gpio::Gpio1::new(p.gpio1, &mut gcr.reg).split();
+/// let mut i2s = hal::i2s::I2sPeripheral::new(
+///     p.i2s,                      // I2S peripheral from the PAC
+///     &mut gcr.reg,               // GCR instance
+///     (
+///         pins.p1_2.into_af1(),   // SCK
+///         pins.p1_3.into_af1(),   // WS
+///         pins.p1_4.into_af1(),   // SDI
+///         pins.p1_5.into_af1(),   // SDO
+///     ),
+///     &clks.pclk,                 // Peripheral clock
+/// )
+///     .sample_rate(16_000)
+///     .word_size(hal::i2s::WordSize::Sixteen)
+///     .channel_mode(hal::i2s::ChannelMode::Stereo)
+///     .build();
+///
+/// // Stream a buffer of packed stereo 16-bit frames to a codec
+/// i2s.write_words(&samples);
+/// ```
+pub struct I2sPeripheral {
+    i2s: crate::pac::I2s,
+    pins: I2sPins,
+    pclk_freq: u32,
+    sample_rate: u32,
+    word_size: WordSize,
+    channel_mode: ChannelMode,
+}
+
+/// A configured I2S peripheral ready for streaming.
+pub struct BuiltI2sPeripheral {
+    i2s: crate::pac::I2s,
+    _pins: I2sPins,
+    pclk_freq: u32,
+    bits: u32,
+}
+
+impl I2sPeripheral {
+    /// Construct a new I2S peripheral.
+    pub fn new(
+        i2s: crate::pac::I2s,
+        reg: &mut crate::gcr::GcrRegisters,
+        pins: I2sPins,
+        clock: &Clock<PeripheralClock>,
+    ) -> Self {
+        unsafe {
+            i2s.enable_clock(&mut reg.gcr);
+        }
+        Self {
+            i2s,
+            pins,
+            pclk_freq: clock.frequency,
+            sample_rate: 16_000,
+            word_size: WordSize::Sixteen,
+            channel_mode: ChannelMode::Stereo,
+        }
+    }
+
+    /// Set the sample rate (frames per second).
+    ///
+    /// Default: `16000`
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Set the size of each sample.
+    ///
+    /// Default: [`WordSize::Sixteen`]
+    pub fn word_size(mut self, word_size: WordSize) -> Self {
+        self.word_size = word_size;
+        self
+    }
+
+    /// Set the channel mode.
+    ///
+    /// Default: [`ChannelMode::Stereo`]
+    pub fn channel_mode(mut self, channel_mode: ChannelMode) -> Self {
+        self.channel_mode = channel_mode;
+        self
+    }
+
+    /// Apply all settings and configure the I2S peripheral.
+    /// This must be called before the I2S peripheral can be used.
+    ///
+    /// ## Panics
+    /// Panics if the sample rate cannot be generated from the peripheral
+    /// clock. Use [`I2sPeripheral::try_build`] to handle this as an error
+    /// instead.
+    pub fn build(self) -> BuiltI2sPeripheral {
+        self.try_build()
+            .expect("I2S sample rate cannot be generated from the PCLK")
+    }
+
+    /// Apply all settings and configure the I2S peripheral, or return a
+    /// [`SampleRateError`] without touching the hardware if the sample rate
+    /// cannot be generated from the peripheral clock (see the clocking
+    /// section of [`I2sPeripheral`]).
+    ///
+    /// Example:
+    /// ```
+    /// // 1 MHz of 32-bit samples needs a 128 MHz bit clock
+    /// let result = hal::i2s::I2sPeripheral::new(p.i2s, &mut gcr.reg, pins, &clks.pclk)
+    ///     .sample_rate(1_000_000)
+    ///     .word_size(hal::i2s::WordSize::ThirtyTwo)
+    ///     .try_build();
+    /// assert_eq!(result.err(), Some(hal::i2s::SampleRateError::TooFast));
+    /// ```
+    pub fn try_build(self) -> Result<BuiltI2sPeripheral, SampleRateError> {
+        let bits: u32 = match self.word_size {
+            WordSize::Eight => 8,
+            WordSize::Sixteen => 16,
+            WordSize::ThirtyTwo => 32,
+        };
+        let clkdiv = compute_clkdiv(self.pclk_freq, bits, self.sample_rate)?;
+        // Reset the channel
+        self.i2s.ctrl0ch0().write(|w| w.rst().set_bit());
+        while self.i2s.ctrl0ch0().read().rst().bit_is_set() {}
+        self.i2s.ctrl0ch0().write(|w| unsafe {
+            match self.word_size {
+                WordSize::Eight => w.wsize().bits(0),
+                WordSize::Sixteen => w.wsize().bits(1),
+                WordSize::ThirtyTwo => w.wsize().bits(2),
+            };
+            match self.channel_mode {
+                ChannelMode::Stereo => w.ch_mode().bits(0),
+                ChannelMode::MonoLeft => w.ch_mode().bits(2),
+                ChannelMode::MonoRight => w.ch_mode().bits(3),
+            };
+            w
+        });
+        // Set the bit clock divisor
+        self.i2s.ctrl1ch0().write(|w| unsafe {
+            w.bits_word()
+                .bits((bits - 1) as u8)
+                .smp_size()
+                .bits((bits - 1) as u8)
+                .clkdiv()
+                .bits(clkdiv)
+        });
+        // Enable the bit clock
+        self.i2s.ctrl1ch0().modify(|_, w| w.en().set_bit());
+        Ok(BuiltI2sPeripheral {
+            i2s: self.i2s,
+            _pins: self.pins,
+            pclk_freq: self.pclk_freq,
+            bits,
+        })
+    }
+}
+
+/// # I2S Methods
+/// These methods are used to stream samples after the I2S peripheral has been
+/// built.
+impl BuiltI2sPeripheral {
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _tx_level(&self) -> u8 {
+        self.i2s.dmach0().read().tx_lvl().bits()
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _rx_level(&self) -> u8 {
+        self.i2s.dmach0().read().rx_lvl().bits()
+    }
+
+    /// Returns the achieved sample rate (frames per second).
+    pub fn sample_rate(&self) -> u32 {
+        let clkdiv = self.i2s.ctrl1ch0().read().clkdiv().bits() as u32;
+        self.pclk_freq / (4 * self.bits * (clkdiv + 1))
+    }
+
+    /// Enable or disable the transmitter.
+    pub fn tx_enable(&mut self, enable: bool) {
+        self.i2s.ctrl0ch0().modify(|_, w| w.tx_en().bit(enable));
+    }
+
+    /// Enable or disable the receiver.
+    pub fn rx_enable(&mut self, enable: bool) {
+        self.i2s.ctrl0ch0().modify(|_, w| w.rx_en().bit(enable));
+    }
+
+    /// Discard all words in the TX and RX FIFOs.
+    pub fn flush_fifos(&mut self) {
+        self.i2s.ctrl0ch0().modify(|_, w| w.flush().set_bit());
+        while self.i2s.ctrl0ch0().read().flush().bit_is_set() {}
+    }
+
+    /// Returns the number of words currently in the TX FIFO.
+    pub fn tx_level(&self) -> u8 {
+        self._tx_level()
+    }
+
+    /// Returns the number of words currently in the RX FIFO.
+    pub fn rx_level(&self) -> u8 {
+        self._rx_level()
+    }
+
+    /// Returns [`true`] if the RX FIFO has overrun, and clears the flag.
+    pub fn take_rx_overrun(&mut self) -> bool {
+        let overrun = self.i2s.intfl().read().rx_ov_ch0().bit_is_set();
+        if overrun {
+            self.i2s.intfl().write(|w| w.rx_ov_ch0().set_bit());
+        }
+        overrun
+    }
+
+    /// Writes FIFO words from a buffer. The transmitter is enabled if it is
+    /// not already. This is a blocking operation.
+    pub fn write_words(&mut self, buffer: &[u32]) {
+        self.tx_enable(true);
+        for word in buffer {
+            while self._tx_level() >= FIFO_DEPTH {}
+            self.i2s
+                .fifoch0()
+                .write(|w| unsafe { w.data().bits(*word) });
+        }
+    }
+
+    /// Reads FIFO words into a buffer. The receiver is enabled if it is not
+    /// already. The entire length of the buffer will be filled. This is a
+    /// blocking operation.
+    pub fn read_words(&mut self, buffer: &mut [u32]) {
+        self.rx_enable(true);
+        for word in buffer {
+            while self._rx_level() == 0 {}
+            *word = self.i2s.fifoch0().read().data().bits();
+        }
+    }
+
+    /// Enable DMA requests from the TX FIFO. A request is issued while the
+    /// TX FIFO holds fewer than `threshold` words.
+    ///
+    /// The DMA channel itself must be configured separately with the address
+    /// from [`BuiltI2sPeripheral::fifo_address`] as the destination.
+    pub fn enable_tx_dma(&mut self, threshold: u8) {
+        self.i2s.dmach0().modify(|_, w| unsafe {
+            w.dma_tx_thd_val()
+                .bits(threshold.min(FIFO_DEPTH))
+                .dma_tx_en()
+                .set_bit()
+        });
+    }
+
+    /// Enable DMA requests from the RX FIFO. A request is issued while the
+    /// RX FIFO holds more than `threshold` words.
+    ///
+    /// The DMA channel itself must be configured separately with the address
+    /// from [`BuiltI2sPeripheral::fifo_address`] as the source.
+    pub fn enable_rx_dma(&mut self, threshold: u8) {
+        self.i2s.dmach0().modify(|_, w| unsafe {
+            w.dma_rx_thd_val()
+                .bits(threshold.min(FIFO_DEPTH))
+                .dma_rx_en()
+                .set_bit()
+        });
+    }
+
+    /// Disable DMA requests from both FIFOs.
+    pub fn disable_dma(&mut self) {
+        self.i2s
+            .dmach0()
+            .modify(|_, w| w.dma_tx_en().clear_bit().dma_rx_en().clear_bit());
+    }
+
+    /// Returns the address of the FIFO register, for use as a DMA source or
+    /// destination.
+    pub fn fifo_address(&self) -> u32 {
+        self.i2s.fifoch0().as_ptr() as u32
+    }
+
+    /// Stop streaming, gate the I2S clock, and return the I2S peripheral
+    /// from the PAC and the pins. The transmitter, receiver and DMA requests
+    /// are disabled and the FIFOs are discarded, so the pins stop toggling.
+    pub fn release(mut self, reg: &mut crate::gcr::GcrRegisters) -> (crate::pac::I2s, I2sPins) {
+        self.disable_dma();
+        self.i2s
+            .ctrl0ch0()
+            .modify(|_, w| w.tx_en().clear_bit().rx_en().clear_bit());
+        self.flush_fifos();
+        unsafe {
+            self.i2s.disable_clock(&mut reg.gcr);
+        }
+        (self.i2s, self._pins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clkdiv_rounds_down() {
+        // 50 MHz / (4 * 16 * 48 kHz) = 16.3, so the rate is ~48.83 kHz
+        assert_eq!(compute_clkdiv(50_000_000, 16, 48_000), Ok(15));
+        assert_eq!(compute_clkdiv(50_000_000, 16, 16_000), Ok(47));
+    }
+
+    #[test]
+    fn clkdiv_range() {
+        assert_eq!(
+            compute_clkdiv(50_000_000, 16, 0),
+            Err(SampleRateError::TooFast)
+        );
+        assert_eq!(compute_clkdiv(128, 32, 1), Ok(0));
+        assert_eq!(compute_clkdiv(127, 32, 1), Err(SampleRateError::TooFast));
+        // 4 * 32 * sample_rate overflows u32
+        assert_eq!(
+            compute_clkdiv(u32::MAX, 32, 40_000_000),
+            Err(SampleRateError::TooFast)
+        );
+        assert_eq!(compute_clkdiv(32 * 65_536, 8, 1), Ok(u16::MAX));
+        assert_eq!(
+            compute_clkdiv(32 * 65_537, 8, 1),
+            Err(SampleRateError::TooSlow)
+        );
+    }
+}
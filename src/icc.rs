@@ -36,6 +36,13 @@ impl Icc {
         self.icc.ctrl().modify(|_, w| w.en().dis());
     }
 
+    /// Return the ICC peripheral from the PAC. The ICC has no peripheral
+    /// clock, and the cache is left enabled or disabled as it is, since
+    /// disabling it only slows down execution from flash.
+    pub fn release(self) -> crate::pac::Icc0 {
+        self.icc
+    }
+
     /// Enable the instruction cache controller.
     #[inline(always)]
     pub fn enable(&mut self) {
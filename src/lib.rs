@@ -6,15 +6,24 @@ pub use cortex_m_rt::entry;
 /// Re-export of the Peripheral Access Crate (PAC) for the MAX78000.
 pub use max78000_pac as pac;
 pub use pac::Interrupt;
+pub use peripherals::Peripherals;
 
 mod private {
     pub trait Sealed {}
 }
 use private::Sealed;
 
+pub mod debug;
 pub mod flc;
 pub mod gcr;
 pub mod gpio;
+pub mod i2s;
 pub mod icc;
+pub mod owm;
+mod peripherals;
+pub mod pt;
+pub mod pwrseq;
+pub mod sema;
+pub mod tmr;
 pub mod trng;
 pub mod uart;
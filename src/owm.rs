@@ -0,0 +1,233 @@
+//! # 1-Wire Master (OWM)
+//!
+//! The 1-Wire master generates all 1-Wire bus timing in hardware, supporting
+//! devices such as the DS18B20 temperature sensor.
+use crate::gcr::{
+    clocks::{Clock, PeripheralClock},
+    ClockForPeripheral,
+};
+use crate::gpio::{Af2, Pin};
+
+/// 1-Wire master errors.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OwmError {
+    /// The peripheral clock cannot be divided down to the 1 MHz timing
+    /// clock of the 1-Wire master (see [`Owm::try_new`]).
+    ClockOutOfRange,
+}
+
+/// Computes the divisor from the peripheral clock frequency to the 1 MHz
+/// timing clock of the 1-Wire master, rounded to the nearest integer.
+/// Returns [`OwmError::ClockOutOfRange`] if the divisor is not in `1..=255`.
+#[inline]
+const fn clk_div_1us(pclk_freq: u32) -> Result<u8, OwmError> {
+    let clk_div = (pclk_freq as u64 + 500_000) / 1_000_000;
+    if clk_div < 1 || clk_div > 255 {
+        return Err(OwmError::ClockOutOfRange);
+    }
+    Ok(clk_div as u8)
+}
+
+/// 1-Wire bus speed.
+pub enum OwmSpeed {
+    /// Standard speed (~15.6 kbps).
+    Standard,
+    /// Overdrive speed (~110 kbps).
+    Overdrive,
+}
+
+/// # 1-Wire Master (OWM) Peripheral
+///
+/// The 1-Wire data line (OWM_IO) is on pin P0.6 using alternate function 2.
+///
+/// The 1-Wire bus is open-drain and requires a pull-up resistor on the data
+/// line (typically 4.7 kΩ to the bus supply). The internal pull-up can be
+/// enabled with [`Owm::internal_pullup`], but it is generally too weak for
+/// long lines or parasitically powered devices.
+///
+/// Example:
+/// ```
+/// let pins = hal::gpio::Gpio0::new(p.gpio0, &mut gcr.reg).split();
+/// let mut owm = hal::owm::Owm::new(
+///     p.owm,                  // OWM peripheral from the PAC
+///     &mut gcr.reg,           // GCR instance
+///     pins.p0_6.into_af2(),   // 1-Wire data pin
+///     &clks.pclk,             // Peripheral clock
+/// );
+/// // Address all devices and start a DS18B20 temperature conversion
+/// if owm.reset_presence() {
+///     owm.write_byte(0xCC); // Skip ROM
+///     owm.write_byte(0x44); // Convert T
+/// }
+/// ```
+pub struct Owm {
+    owm: crate::pac::Owm,
+    _io_pin: Pin<0, 6, Af2>,
+}
+
+impl Owm {
+    /// Construct a new 1-Wire master peripheral at standard speed.
+    ///
+    /// ## Panics
+    /// Panics if the peripheral clock is outside the supported range (see
+    /// [`Owm::try_new`]).
+    pub fn new(
+        owm: crate::pac::Owm,
+        reg: &mut crate::gcr::GcrRegisters,
+        io_pin: Pin<0, 6, Af2>,
+        clock: &Clock<PeripheralClock>,
+    ) -> Self {
+        Self::try_new(owm, reg, io_pin, clock)
+            .expect("PCLK cannot be divided down to the 1-Wire timing clock")
+    }
+
+    /// Construct a new 1-Wire master peripheral like [`Owm::new`], but
+    /// return [`OwmError::ClockOutOfRange`] if the peripheral clock is
+    /// outside the supported range.
+    ///
+    /// The 1-Wire timing is derived from a 1 µs tick, which is the PCLK
+    /// divided by an integer from 1 to 255 (rounded to the nearest
+    /// integer). The PCLK must therefore be at least 0.5 MHz and below
+    /// 255.5 MHz. For accurate bus timing, the PCLK should be a whole
+    /// number of MHz. Otherwise the tick is longer or shorter than 1 µs,
+    /// e.g. 8.5% longer for a 3.6864 MHz PCLK (IBRO / 2) divided by 4.
+    ///
+    /// Example:
+    /// ```
+    /// let owm = hal::owm::Owm::try_new(p.owm, &mut gcr.reg, pins.p0_6.into_af2(), &clks.pclk)
+    ///     .expect("PCLK not supported by the 1-Wire master");
+    /// ```
+    pub fn try_new(
+        owm: crate::pac::Owm,
+        reg: &mut crate::gcr::GcrRegisters,
+        io_pin: Pin<0, 6, Af2>,
+        clock: &Clock<PeripheralClock>,
+    ) -> Result<Self, OwmError> {
+        let clk_div = clk_div_1us(clock.frequency)?;
+        unsafe {
+            owm.enable_clock(&mut reg.gcr);
+        }
+        // The 1-Wire timing is derived from a 1 MHz clock
+        owm.clk_div_1us()
+            .write(|w| unsafe { w.divisor().bits(clk_div) });
+        owm.cfg().write(|w| w.single_bit_mode().clear_bit());
+        // Clear stale interrupts
+        owm.intfl().write(|w| unsafe { w.bits(0x1F) });
+        Ok(Self {
+            owm,
+            _io_pin: io_pin,
+        })
+    }
+
+    /// Gate the 1-Wire master clock, and return the OWM peripheral from the
+    /// PAC and the I/O pin. The pin is left in its alternate function, so
+    /// the bus stays idle (pulled high by its pull-up resistor).
+    pub fn release(self, reg: &mut crate::gcr::GcrRegisters) -> (crate::pac::Owm, Pin<0, 6, Af2>) {
+        unsafe {
+            self.owm.disable_clock(&mut reg.gcr);
+        }
+        (self.owm, self._io_pin)
+    }
+
+    /// Set the speed of the 1-Wire bus.
+    ///
+    /// Default: [`OwmSpeed::Standard`]
+    pub fn set_speed(&mut self, speed: OwmSpeed) {
+        match speed {
+            OwmSpeed::Standard => self.owm.cfg().modify(|_, w| w.overdrive().clear_bit()),
+            OwmSpeed::Overdrive => self.owm.cfg().modify(|_, w| w.overdrive().set_bit()),
+        };
+    }
+
+    /// Enable or disable the internal pull-up on the data line.
+    ///
+    /// Default: disabled
+    pub fn internal_pullup(&mut self, enable: bool) {
+        self.owm
+            .cfg()
+            .modify(|_, w| w.int_pullup_enable().bit(enable));
+    }
+
+    /// Send a reset pulse on the 1-Wire bus. This is a blocking operation.
+    ///
+    /// Returns [`true`] if a device responded with a presence pulse.
+    pub fn reset_presence(&mut self) -> bool {
+        self.owm.intfl().write(|w| w.ow_reset_done().set_bit());
+        self.owm
+            .ctrl_stat()
+            .modify(|_, w| w.start_ow_reset().set_bit());
+        while self.owm.intfl().read().ow_reset_done().bit_is_clear() {}
+        self.owm.intfl().write(|w| w.ow_reset_done().set_bit());
+        self.owm.ctrl_stat().read().presence_detect().bit_is_set()
+    }
+
+    /// Send and simultaneously receive a time slot on the 1-Wire bus.
+    #[doc(hidden)]
+    #[inline]
+    fn _touch(&mut self, data: u8) -> u8 {
+        self.owm
+            .intfl()
+            .write(|w| w.tx_data_empty().set_bit().rx_data_ready().set_bit());
+        self.owm.data().write(|w| unsafe { w.tx_rx().bits(data) });
+        while self.owm.intfl().read().tx_data_empty().bit_is_clear() {}
+        while self.owm.intfl().read().rx_data_ready().bit_is_clear() {}
+        let data = self.owm.data().read().tx_rx().bits();
+        self.owm
+            .intfl()
+            .write(|w| w.tx_data_empty().set_bit().rx_data_ready().set_bit());
+        data
+    }
+
+    /// Writes a single bit. This is a blocking operation.
+    pub fn write_bit(&mut self, bit: bool) {
+        self.owm.cfg().modify(|_, w| w.single_bit_mode().set_bit());
+        self._touch(bit as u8);
+    }
+
+    /// Reads a single bit. This is a blocking operation.
+    pub fn read_bit(&mut self) -> bool {
+        self.owm.cfg().modify(|_, w| w.single_bit_mode().set_bit());
+        self._touch(1) & 1 != 0
+    }
+
+    /// Writes a single byte (LSB first). This is a blocking operation.
+    pub fn write_byte(&mut self, byte: u8) {
+        self.owm
+            .cfg()
+            .modify(|_, w| w.single_bit_mode().clear_bit());
+        self._touch(byte);
+    }
+
+    /// Reads a single byte (LSB first). This is a blocking operation.
+    pub fn read_byte(&mut self) -> u8 {
+        self.owm
+            .cfg()
+            .modify(|_, w| w.single_bit_mode().clear_bit());
+        // Reading is done by writing all 1s and sampling the bus
+        self._touch(0xFF)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clk_div_1us_rounding() {
+        assert_eq!(clk_div_1us(50_000_000), Ok(50));
+        assert_eq!(clk_div_1us(1_000_000), Ok(1));
+        // IBRO / 2 rounds to the nearest divisor
+        assert_eq!(clk_div_1us(3_686_400), Ok(4));
+        assert_eq!(clk_div_1us(3_400_000), Ok(3));
+    }
+
+    #[test]
+    fn clk_div_1us_range() {
+        assert_eq!(clk_div_1us(499_999), Err(OwmError::ClockOutOfRange));
+        assert_eq!(clk_div_1us(500_000), Ok(1));
+        assert_eq!(clk_div_1us(255_499_999), Ok(255));
+        assert_eq!(clk_div_1us(255_500_000), Err(OwmError::ClockOutOfRange));
+        assert_eq!(clk_div_1us(u32::MAX), Err(OwmError::ClockOutOfRange));
+    }
+}
@@ -0,0 +1,97 @@
+//! # HAL Peripherals
+//!
+//! Singleton access to all peripherals, with the [`Gcr`] already constructed.
+use crate::gcr::Gcr;
+use crate::pac;
+
+macro_rules! peripherals {
+    ($($field:ident: $ty:ident),* $(,)?) => {
+        /// # HAL Peripherals
+        ///
+        /// All peripherals of the chip, taken from the PAC. The GCR and LPGCR
+        /// are wrapped in a constructed [`Gcr`], and every other peripheral is
+        /// passed through as-is from the PAC so that it can be used with a HAL
+        /// constructor or, for peripherals the HAL does not wrap yet, directly.
+        ///
+        /// Like [`pac::Peripherals`], this can only be taken once, so
+        /// peripherals can never be aliased.
+        ///
+        /// Example:
+        /// ```
+        /// let mut p = hal::Peripherals::take().unwrap();
+        /// let clks = p.gcr.sys_clk.set_source(&mut p.gcr.reg, &ipo).freeze();
+        /// let pins = hal::gpio::Gpio0::new(p.gpio0, &mut p.gcr.reg).split();
+        /// // Raw PAC access for peripherals the HAL does not wrap
+        /// let wdt = p.wdt0;
+        /// ```
+        pub struct Peripherals {
+            /// Global control registers (GCR and LPGCR).
+            pub gcr: Gcr,
+            $(pub $field: pac::$ty,)*
+        }
+
+        impl Peripherals {
+            /// Take the peripherals and construct the [`Gcr`]. Returns
+            /// [`None`] if the peripherals (from this function or
+            /// [`pac::Peripherals::take`]) have already been taken.
+            pub fn take() -> Option<Self> {
+                let p = pac::Peripherals::take()?;
+                Some(Self {
+                    gcr: Gcr::new(p.gcr, p.lpgcr),
+                    $($field: p.$field,)*
+                })
+            }
+        }
+    };
+}
+
+peripherals! {
+    adc: Adc,
+    aes: Aes,
+    aeskeys: Aeskeys,
+    cameraif: Cameraif,
+    crc: Crc,
+    dma: Dma,
+    dvs: Dvs,
+    fcr: Fcr,
+    flc: Flc,
+    gcfr: Gcfr,
+    gpio0: Gpio0,
+    gpio1: Gpio1,
+    gpio2: Gpio2,
+    i2c0: I2c0,
+    i2c1: I2c1,
+    i2c2: I2c2,
+    i2s: I2s,
+    icc0: Icc0,
+    lpcmp: Lpcmp,
+    mcr: Mcr,
+    owm: Owm,
+    pt0: Pt0,
+    pt1: Pt1,
+    pt2: Pt2,
+    pt3: Pt3,
+    ptg: Ptg,
+    pwrseq: Pwrseq,
+    rtc: Rtc,
+    sema: Sema,
+    simo: Simo,
+    sir: Sir,
+    spi0: Spi0,
+    spi1: Spi1,
+    tmr0: Tmr0,
+    tmr1: Tmr1,
+    tmr2: Tmr2,
+    tmr3: Tmr3,
+    tmr4: Tmr4,
+    tmr5: Tmr5,
+    trimsir: Trimsir,
+    trng: Trng,
+    uart0: Uart0,
+    uart1: Uart1,
+    uart2: Uart2,
+    uart3: Uart3,
+    wdt0: Wdt0,
+    wdt1: Wdt1,
+    wut: Wut,
+}
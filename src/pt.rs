@@ -0,0 +1,261 @@
+//! # Pulse Train Engine (PT)
+//!
+//! The pulse train engine generates square waves or repeating bit patterns
+//! on an output pin without CPU intervention.
+use core::ops::Deref;
+
+use crate::gcr::{
+    clocks::{Clock, PeripheralClock},
+    ClockForPeripheral,
+};
+use crate::gpio::{Af2, Pin};
+use paste::paste;
+
+/// Maximum value of the 27-bit rate control field.
+const RATE_CONTROL_MAX: u32 = (1 << 27) - 1;
+
+/// Pulse train configuration errors.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PulseTrainError {
+    /// The requested frequency or bit rate cannot be derived from the
+    /// peripheral clock.
+    InvalidRate,
+    /// The requested pattern length is not between 2 and 32 bits.
+    InvalidLength,
+}
+
+/// # Pulse Train (PT) Peripheral
+///
+/// Each pulse train channel drives a single output pin on alternate
+/// function 2:
+///
+/// | Channel | Pin   |
+/// |---------|-------|
+/// | PT0     | P0.18 |
+/// | PT1     | P0.19 |
+/// | PT2     | P0.16 |
+/// | PT3     | P0.17 |
+///
+/// All channels are clocked from the peripheral clock (PCLK). In pattern
+/// mode, each bit is shifted out for `PCLK / bit_rate` cycles. In square
+/// wave mode, the output toggles every `PCLK / (2 * frequency)` cycles. The
+/// resulting frequency is truncated to the nearest achievable value, so the
+/// resolution is coarser at higher frequencies.
+///
+/// ## Example
+/// ```
+/// let pins = hal::gpio::Gpio0::new(p.gpio0, &mut gcr.reg).split();
+/// let mut pt = hal::pt::PulseTrain::pt0(
+///     p.pt0,                    // Pulse train channel from the PAC
+///     &mut gcr.reg,             // GCR instance
+///     pins.p0_18.into_af2(),    // Output pin
+///     &clks.pclk,               // Peripheral clock
+/// );
+/// // Output a 1 kHz square wave
+/// pt.square_wave(1_000).unwrap();
+/// pt.start();
+///
+/// // Output the pattern 0b1011 ten times at 9600 bits per second
+/// pt.stop();
+/// pt.pattern(0b1011, 4, 9600).unwrap();
+/// pt.repeat(10);
+/// pt.start();
+/// while !pt.is_complete() {}
+/// pt.clear_complete();
+/// ```
+pub struct PulseTrain<PT, PIN> {
+    pt: PT,
+    _pin: PIN,
+    pclk_freq: u32,
+}
+
+/// Pins that can be used as the output of a pulse train channel.
+pub trait PtPin<PT>: crate::Sealed {}
+
+/// Index of a pulse train channel within the global PT registers.
+#[doc(hidden)]
+pub trait PtChannel {
+    const CHANNEL: u8;
+}
+
+// All pulse train channels are derived from the same register block
+type PtRegisterBlock = crate::pac::pt0::RegisterBlock;
+
+macro_rules! pt {
+    ($pt:ident, $channel:expr, pin: $pin:ty) => {
+        paste! {
+            use crate::pac::$pt;
+
+            impl PtChannel for $pt {
+                const CHANNEL: u8 = $channel;
+            }
+
+            impl crate::Sealed for $pin {}
+            impl PtPin<$pt> for $pin {}
+
+            impl PulseTrain<$pt, $pin> {
+                #[doc = "Construct a new "]
+                #[doc = stringify!([<$pt:upper>])]
+                #[doc = " pulse train channel."]
+                pub fn [<$pt:lower>](
+                    pt: $pt,
+                    reg: &mut crate::gcr::GcrRegisters,
+                    pin: $pin,
+                    clock: &Clock<PeripheralClock>,
+                ) -> Self {
+                    // Enable the pulse train peripheral clock (shared by all channels)
+                    unsafe { pt.enable_clock(&mut reg.gcr); }
+                    let s = Self {
+                        pt,
+                        _pin: pin,
+                        pclk_freq: clock.frequency,
+                    };
+                    s._disable();
+                    s
+                }
+            }
+        }
+    };
+}
+
+pt!(Pt0, 0, pin: Pin<0, 18, Af2>);
+pt!(Pt1, 1, pin: Pin<0, 19, Af2>);
+pt!(Pt2, 2, pin: Pin<0, 16, Af2>);
+pt!(Pt3, 3, pin: Pin<0, 17, Af2>);
+
+/// # Pulse Train Methods
+impl<PT, PIN> PulseTrain<PT, PIN>
+where
+    PT: Deref<Target = PtRegisterBlock> + PtChannel,
+{
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _enable(&self) {
+        // Safety: Concurrent write access to the PT atomic safe enable register is safe
+        let ptg = unsafe { &*crate::pac::Ptg::ptr() };
+        ptg.safe_en().write(|w| unsafe { w.bits(1 << PT::CHANNEL) });
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _disable(&self) {
+        // Safety: Concurrent write access to the PT atomic safe disable register is safe
+        let ptg = unsafe { &*crate::pac::Ptg::ptr() };
+        ptg.safe_dis()
+            .write(|w| unsafe { w.bits(1 << PT::CHANNEL) });
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _rate_control(&self, rate: u32) -> Result<u32, PulseTrainError> {
+        if rate == 0 {
+            return Err(PulseTrainError::InvalidRate);
+        }
+        let rate_control = self.pclk_freq / rate;
+        if rate_control == 0 || rate_control > RATE_CONTROL_MAX {
+            return Err(PulseTrainError::InvalidRate);
+        }
+        Ok(rate_control)
+    }
+
+    /// Configure the channel to output a square wave at the given frequency
+    /// (in Hz). The channel must be stopped before it is reconfigured.
+    pub fn square_wave(&mut self, frequency: u32) -> Result<(), PulseTrainError> {
+        let rate_control = self._rate_control(frequency.saturating_mul(2))?;
+        self.pt
+            .rate_length()
+            .write(|w| unsafe { w.rate_control().bits(rate_control).mode().square_wave() });
+        Ok(())
+    }
+
+    /// Configure the channel to output the lowest `length` bits of `pattern`
+    /// (LSB first) at the given bit rate (in bits per second). `length` must
+    /// be between 2 and 32 bits. The channel must be stopped before it is
+    /// reconfigured.
+    pub fn pattern(
+        &mut self,
+        pattern: u32,
+        length: u8,
+        bit_rate: u32,
+    ) -> Result<(), PulseTrainError> {
+        if !(2..=32).contains(&length) {
+            return Err(PulseTrainError::InvalidLength);
+        }
+        let rate_control = self._rate_control(bit_rate)?;
+        // A mode of 0 represents a 32-bit pattern
+        let mode = length % 32;
+        self.pt.train().write(|w| unsafe { w.bits(pattern) });
+        self.pt
+            .rate_length()
+            .write(|w| unsafe { w.rate_control().bits(rate_control).mode().bits(mode) });
+        Ok(())
+    }
+
+    /// Set the number of times the pattern or square wave period is repeated
+    /// before the channel stops. A count of `0` repeats forever.
+    ///
+    /// Default: `0`
+    pub fn repeat(&mut self, count: u16) {
+        self.pt
+            .loop_()
+            .modify(|_, w| unsafe { w.count().bits(count) });
+    }
+
+    /// Set the delay (in PCLK cycles, up to 12 bits) inserted between each
+    /// repetition.
+    ///
+    /// Default: `0`
+    pub fn loop_delay(&mut self, cycles: u16) {
+        self.pt
+            .loop_()
+            .modify(|_, w| unsafe { w.delay().bits(cycles & 0x0FFF) });
+    }
+
+    /// Start the pulse train output.
+    pub fn start(&mut self) {
+        self.clear_complete();
+        self._enable();
+    }
+
+    /// Stop the pulse train output.
+    pub fn stop(&mut self) {
+        self._disable();
+    }
+
+    /// Stop the pulse train, and return the channel from the PAC and the
+    /// output pin. The pulse train peripheral clock is shared by all
+    /// channels, so it is left enabled.
+    pub fn release(self) -> (PT, PIN) {
+        self._disable();
+        (self.pt, self._pin)
+    }
+
+    /// Returns [`true`] if the pulse train is currently running.
+    pub fn is_running(&self) -> bool {
+        // Safety: Concurrent read access to the PT enable register is safe
+        let ptg = unsafe { &*crate::pac::Ptg::ptr() };
+        ptg.enable().read().bits() & (1 << PT::CHANNEL) != 0
+    }
+
+    /// Returns [`true`] if the pulse train has stopped after completing all
+    /// of its repetitions.
+    pub fn is_complete(&self) -> bool {
+        // Safety: Concurrent read access to the PT interrupt flag register is safe
+        let ptg = unsafe { &*crate::pac::Ptg::ptr() };
+        ptg.intfl().read().bits() & (1 << PT::CHANNEL) != 0
+    }
+
+    /// Returns the interrupt vector of the pulse train engine, e.g. for
+    /// unmasking it in the NVIC. All channels share a single interrupt.
+    pub fn interrupt(&self) -> crate::Interrupt {
+        crate::Interrupt::PT
+    }
+
+    /// Clear the completion flag of the pulse train.
+    pub fn clear_complete(&mut self) {
+        // Safety: Interrupt flags are write 1 to clear, so only this channel is affected
+        let ptg = unsafe { &*crate::pac::Ptg::ptr() };
+        ptg.intfl().write(|w| unsafe { w.bits(1 << PT::CHANNEL) });
+    }
+}
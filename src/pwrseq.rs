@@ -0,0 +1,88 @@
+//! # Power Sequencer (PWRSEQ)
+//!
+//! The power sequencer is part of the always-on domain, so its registers
+//! retain their values across most resets.
+
+/// Number of retained general purpose registers.
+pub const RETAINED_REGISTER_COUNT: usize = 2;
+
+/// # Power Sequencer (PWRSEQ) Peripheral
+///
+/// ## Retained Registers
+/// The power sequencer contains [`RETAINED_REGISTER_COUNT`] general purpose
+/// registers (`GP0` and `GP1`). They are only cleared by a power-on reset,
+/// so they survive:
+/// - System resets, including those requested by software and the watchdog
+/// - Soft and peripheral resets
+/// - Wakeups from low power modes
+///
+/// `GP0` holds the boot reason (see [`Pwrseq::boot_reason`]), which can be
+/// used to hand off between firmware images, such as rebooting from an
+/// application into a bootloader.
+///
+/// Example:
+/// ```
+/// const ENTER_BOOTLOADER: u32 = 0xB007_10AD;
+///
+/// let mut pwrseq = Pwrseq::new(p.pwrseq);
+/// // In the bootloader, check why the chip was reset
+/// if pwrseq.boot_reason() == ENTER_BOOTLOADER {
+///     pwrseq.set_boot_reason(0);
+///     // ... stay in the bootloader ...
+/// }
+///
+/// // In the application, reboot into the bootloader
+/// pwrseq.reset_with_boot_reason(ENTER_BOOTLOADER);
+/// ```
+pub struct Pwrseq {
+    pwrseq: crate::pac::Pwrseq,
+}
+
+impl Pwrseq {
+    /// Create a new power sequencer peripheral instance.
+    pub fn new(pwrseq: crate::pac::Pwrseq) -> Self {
+        Self { pwrseq }
+    }
+
+    /// Read retained register `n`.
+    ///
+    /// ## Panics
+    /// Panics if `n` is not less than [`RETAINED_REGISTER_COUNT`].
+    pub fn read_retained(&self, n: usize) -> u32 {
+        match n {
+            0 => self.pwrseq.gp0().read().bits(),
+            1 => self.pwrseq.gp1().read().bits(),
+            _ => panic!("Invalid retained register number"),
+        }
+    }
+
+    /// Write retained register `n`.
+    ///
+    /// ## Panics
+    /// Panics if `n` is not less than [`RETAINED_REGISTER_COUNT`].
+    pub fn write_retained(&mut self, n: usize, value: u32) {
+        // Safety: All bits of the general purpose registers are writable
+        match n {
+            0 => self.pwrseq.gp0().write(|w| unsafe { w.bits(value) }),
+            1 => self.pwrseq.gp1().write(|w| unsafe { w.bits(value) }),
+            _ => panic!("Invalid retained register number"),
+        };
+    }
+
+    /// Returns the boot reason stored before the last reset, or `0` after a
+    /// power-on reset.
+    pub fn boot_reason(&self) -> u32 {
+        self.read_retained(0)
+    }
+
+    /// Store a boot reason that survives all resets except a power-on reset.
+    pub fn set_boot_reason(&mut self, reason: u32) {
+        self.write_retained(0, reason);
+    }
+
+    /// Store a boot reason and perform a system reset.
+    pub fn reset_with_boot_reason(&mut self, reason: u32) -> ! {
+        self.set_boot_reason(reason);
+        cortex_m::peripheral::SCB::sys_reset()
+    }
+}
@@ -0,0 +1,102 @@
+//! # Semaphore (SEMA)
+//!
+//! The semaphore peripheral provides hardware semaphores that can be
+//! atomically set and cleared, allowing the Arm and RISC-V cores to
+//! cooperate when accessing shared resources.
+
+/// Number of hardware semaphores.
+pub const SEMAPHORE_COUNT: usize = 8;
+
+/// # Semaphore (SEMA) Peripheral
+///
+/// The peripheral contains [`SEMAPHORE_COUNT`] semaphores. Reading a
+/// semaphore atomically tests and sets it, so acquiring a semaphore is a
+/// single non-blocking operation. How semaphores are allocated between cores
+/// is left entirely to software.
+///
+/// Example:
+/// ```
+/// let sema = Sema::new(p.sema, &mut gcr.reg);
+/// // Try to take semaphore 0 without blocking
+/// if sema.try_acquire(0) {
+///     // ... access the shared resource ...
+///     sema.release(0);
+/// }
+/// // Spin until semaphore 1 is taken, released when the guard is dropped
+/// {
+///     let _guard = sema.lock(1);
+///     // ... access the shared resource ...
+/// }
+/// ```
+pub struct Sema {
+    sema: crate::pac::Sema,
+}
+
+impl Sema {
+    /// Create a new semaphore peripheral instance.
+    pub fn new(sema: crate::pac::Sema, reg: &mut crate::gcr::GcrRegisters) -> Self {
+        use crate::gcr::ClockForPeripheral;
+        unsafe {
+            sema.enable_clock(&mut reg.gcr);
+        }
+        Self { sema }
+    }
+
+    /// Attempt to acquire semaphore `n`. This is a non-blocking operation.
+    ///
+    /// Returns [`true`] if the semaphore was free and is now held by the
+    /// caller, [`false`] if it was already taken.
+    ///
+    /// ## Panics
+    /// Panics if `n` is not less than [`SEMAPHORE_COUNT`].
+    #[inline(always)]
+    pub fn try_acquire(&self, n: usize) -> bool {
+        // Reading the semaphore sets it and returns the prior value
+        self.sema.semaphores(n).read().sema().bit_is_clear()
+    }
+
+    /// Release semaphore `n`.
+    ///
+    /// ## Panics
+    /// Panics if `n` is not less than [`SEMAPHORE_COUNT`].
+    #[inline(always)]
+    pub fn release(&self, n: usize) {
+        // Safety: Writing 0 to a semaphore clears it
+        self.sema.semaphores(n).write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Returns [`true`] if semaphore `n` is currently taken. Unlike
+    /// [`Sema::try_acquire`], this does not modify the semaphore.
+    ///
+    /// ## Panics
+    /// Panics if `n` is not less than [`SEMAPHORE_COUNT`].
+    #[inline(always)]
+    pub fn is_taken(&self, n: usize) -> bool {
+        assert!(n < SEMAPHORE_COUNT, "Invalid semaphore number");
+        self.sema.status().read().bits() & (1 << n) != 0
+    }
+
+    /// Spin until semaphore `n` is acquired. The semaphore is released when
+    /// the returned [`SemaGuard`] is dropped.
+    ///
+    /// ## Panics
+    /// Panics if `n` is not less than [`SEMAPHORE_COUNT`].
+    #[inline]
+    pub fn lock(&self, n: usize) -> SemaGuard<'_> {
+        while !self.try_acquire(n) {}
+        SemaGuard { sema: self, n }
+    }
+}
+
+/// Spin-lock guard for a held hardware semaphore. The semaphore is released
+/// when the guard is dropped.
+pub struct SemaGuard<'a> {
+    sema: &'a Sema,
+    n: usize,
+}
+
+impl Drop for SemaGuard<'_> {
+    fn drop(&mut self) {
+        self.sema.release(self.n);
+    }
+}
@@ -0,0 +1,385 @@
+//! # Timers (TMR)
+//!
+//! The general purpose timers can be cascaded into a single 32-bit counter
+//! clocked from the peripheral clock.
+use core::ops::Deref;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::gcr::{
+    clocks::{Clock, PeripheralClock},
+    ClockForPeripheral,
+};
+use embedded_hal_nb::nb;
+use paste::paste;
+
+// All timers are derived from the same register block
+type TmrRegisterBlock = crate::pac::tmr0::RegisterBlock;
+
+/// Error returned by [`Monotonic::with_timeout`].
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TimeoutError<E> {
+    /// The operation did not complete within the timeout.
+    TimedOut,
+    /// The operation failed with its own error.
+    Other(E),
+}
+
+/// Timers that can back a [`Monotonic`] counter.
+#[doc(hidden)]
+pub trait MonotonicTimer: Deref<Target = TmrRegisterBlock> {
+    /// Register block of the timer, for use in its interrupt handler.
+    fn registers() -> &'static TmrRegisterBlock;
+    /// Overflow counter of the timer, shared with its interrupt handler.
+    fn overflows() -> &'static AtomicU32;
+    /// Interrupt vector of the timer.
+    const INTERRUPT: crate::Interrupt;
+}
+
+/// # Monotonic Uptime Counter
+///
+/// Counts the time since it was constructed using one of the 32-bit timers
+/// (TMR0 - TMR3), clocked directly from the peripheral clock. The 32-bit
+/// count is extended to 64 bits by counting timer overflows in the timer's
+/// interrupt handler, which must call [`Monotonic::on_interrupt`] and must be
+/// unmasked in the NVIC.
+///
+/// The backing timer is consumed and used exclusively by the counter.
+///
+/// Example:
+/// ```
+/// let mono = hal::tmr::Monotonic::new(
+///     p.tmr0,         // Timer peripheral from the PAC
+///     &mut gcr.reg,   // GCR instance
+///     &clks.pclk,     // Peripheral clock
+/// );
+/// unsafe { cortex_m::peripheral::NVIC::unmask(mono.interrupt()) };
+///
+/// let start = mono.now_ms();
+///
+/// #[interrupt]
+/// fn TMR0() {
+///     hal::tmr::Monotonic::<hal::pac::Tmr0>::on_interrupt();
+/// }
+/// ```
+pub struct Monotonic<TMR> {
+    _tmr: TMR,
+    tick_freq: u32,
+}
+
+macro_rules! monotonic {
+    ($tmr:ident) => {
+        paste! {
+            static [<$tmr:upper _OVERFLOWS>]: AtomicU32 = AtomicU32::new(0);
+
+            impl MonotonicTimer for crate::pac::$tmr {
+                const INTERRUPT: crate::Interrupt = crate::Interrupt::[<$tmr:upper>];
+
+                #[inline(always)]
+                fn registers() -> &'static TmrRegisterBlock {
+                    unsafe { &*crate::pac::$tmr::ptr() }
+                }
+
+                #[inline(always)]
+                fn overflows() -> &'static AtomicU32 {
+                    &[<$tmr:upper _OVERFLOWS>]
+                }
+            }
+
+            impl Monotonic<crate::pac::$tmr> {
+                /// Construct and start a new monotonic counter.
+                pub fn new(
+                    tmr: crate::pac::$tmr,
+                    reg: &mut crate::gcr::GcrRegisters,
+                    clock: &Clock<PeripheralClock>,
+                ) -> Self {
+                    unsafe { tmr.enable_clock(&mut reg.gcr); }
+                    Self::_start(tmr, clock.frequency)
+                }
+
+                /// Stop the counter, gate the timer clock, and return the
+                /// timer peripheral from the PAC. The overflow interrupt is
+                /// disabled, and the counter restarts from zero when it is
+                /// constructed again.
+                pub fn release(self, reg: &mut crate::gcr::GcrRegisters) -> crate::pac::$tmr {
+                    let tmr = self._tmr;
+                    tmr.ctrl1().modify(|_, w| w.ie_a().clear_bit());
+                    tmr.ctrl0().modify(|_, w| w.en_a().clear_bit());
+                    while tmr.ctrl1().read().clken_a().bit_is_set() {}
+                    unsafe { tmr.disable_clock(&mut reg.gcr); }
+                    tmr
+                }
+            }
+        }
+    };
+}
+
+monotonic!(Tmr0);
+monotonic!(Tmr1);
+monotonic!(Tmr2);
+monotonic!(Tmr3);
+
+impl<TMR> Monotonic<TMR>
+where
+    TMR: MonotonicTimer,
+{
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _start(tmr: TMR, tick_freq: u32) -> Self {
+        // Disable the timer before reconfiguring it
+        tmr.ctrl0().modify(|_, w| w.en_a().clear_bit());
+        while tmr.ctrl1().read().clken_a().bit_is_set() {}
+        // Cascade both 16-bit halves into a single 32-bit timer on PCLK
+        tmr.ctrl1()
+            .write(|w| unsafe { w.cascade().set_bit().clksel_a().bits(0) });
+        tmr.ctrl0()
+            .write(|w| w.mode_a().continuous().clkdiv_a().div_by_1());
+        tmr.cnt().write(|w| unsafe { w.bits(0) });
+        tmr.cmp().write(|w| unsafe { w.bits(u32::MAX) });
+        TMR::overflows().store(0, Ordering::Relaxed);
+        // Clear stale interrupts and enable the overflow interrupt
+        tmr.intfl().write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        tmr.ctrl1().modify(|_, w| w.ie_a().set_bit());
+        // Enable the timer clock, then the timer
+        tmr.ctrl0().modify(|_, w| w.clken_a().set_bit());
+        while tmr.ctrl1().read().clkrdy_a().bit_is_clear() {}
+        tmr.ctrl0().modify(|_, w| w.en_a().set_bit());
+        while tmr.ctrl1().read().clken_a().bit_is_clear() {}
+        Self {
+            _tmr: tmr,
+            tick_freq,
+        }
+    }
+
+    /// Handle the timer overflow interrupt. This must be called from the
+    /// interrupt handler of the backing timer.
+    #[inline]
+    pub fn on_interrupt() {
+        let tmr = TMR::registers();
+        if tmr.intfl().read().irq_a().bit_is_set() {
+            // Interrupt flags are write 1 to clear
+            tmr.intfl().write(|w| w.irq_a().set_bit());
+            let overflows = TMR::overflows();
+            overflows.store(overflows.load(Ordering::Relaxed) + 1, Ordering::Release);
+        }
+    }
+
+    /// Returns the interrupt vector of the backing timer, e.g. for unmasking
+    /// it in the NVIC.
+    pub fn interrupt(&self) -> crate::Interrupt {
+        TMR::INTERRUPT
+    }
+
+    /// Returns the number of timer ticks since the counter was started.
+    pub fn now_ticks(&self) -> u64 {
+        loop {
+            let high = TMR::overflows().load(Ordering::Acquire);
+            let mut low = self._tmr.cnt().read().bits();
+            // An overflow may be pending if interrupts are masked. The flag
+            // is set as the count reaches its maximum, so it only counts once
+            // the count has wrapped.
+            let mut pending = self._tmr.intfl().read().irq_a().bit_is_set();
+            if pending {
+                low = self._tmr.cnt().read().bits();
+                pending = low < u32::MAX / 2;
+            }
+            if TMR::overflows().load(Ordering::Acquire) == high {
+                // The counter wraps after u32::MAX ticks
+                return (high as u64 + pending as u64) * u32::MAX as u64 + low as u64;
+            }
+        }
+    }
+
+    /// Returns the frequency (in Hz) of the timer ticks.
+    pub fn tick_frequency(&self) -> u32 {
+        self.tick_freq
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _ticks_to(&self, scale: u64) -> u64 {
+        let ticks = self.now_ticks();
+        let freq = self.tick_freq as u64;
+        ticks / freq * scale + ticks % freq * scale / freq
+    }
+
+    /// Returns the number of milliseconds since the counter was started.
+    pub fn now_ms(&self) -> u64 {
+        self._ticks_to(1_000)
+    }
+
+    /// Returns the number of microseconds since the counter was started.
+    pub fn now_us(&self) -> u64 {
+        self._ticks_to(1_000_000)
+    }
+
+    /// Repeatedly polls a non-blocking operation until it completes, fails,
+    /// or `timeout_us` microseconds have passed.
+    ///
+    /// The operation must be a non-blocking poll that returns
+    /// [`nb::Error::WouldBlock`] while it is not done yet (like the
+    /// [`embedded_hal_nb`] traits). If it blocks instead, the timeout is only
+    /// checked after it returns and cannot interrupt it.
+    ///
+    /// Example:
+    /// ```
+    /// use embedded_hal_nb::serial::Read;
+    /// // Wait up to 100 ms for a byte from the UART
+    /// match mono.with_timeout(100_000, || uart.read()) {
+    ///     Ok(byte) => { /* ... */ }
+    ///     Err(hal::tmr::TimeoutError::TimedOut) => { /* ... */ }
+    ///     Err(hal::tmr::TimeoutError::Other(e)) => { /* ... */ }
+    /// }
+    /// ```
+    pub fn with_timeout<T, E>(
+        &self,
+        timeout_us: u64,
+        mut op: impl FnMut() -> nb::Result<T, E>,
+    ) -> Result<T, TimeoutError<E>> {
+        let start = self.now_us();
+        loop {
+            match op() {
+                Ok(value) => return Ok(value),
+                Err(nb::Error::Other(e)) => return Err(TimeoutError::Other(e)),
+                Err(nb::Error::WouldBlock) => {}
+            }
+            if self.now_us() - start >= timeout_us {
+                return Err(TimeoutError::TimedOut);
+            }
+        }
+    }
+}
+
+/// A callback registered with [`SoftTimers`].
+#[derive(Clone, Copy)]
+struct SoftTimer {
+    callback: fn(),
+    deadline_ms: u64,
+    /// Period of a periodic timer, or `0` for a one-shot timer.
+    period_ms: u64,
+}
+
+/// # Software Timers
+///
+/// Runs up to `N` periodic or one-shot callbacks with millisecond periods,
+/// using the time of a single [`Monotonic`] counter. This is a lightweight
+/// cooperative scheduler, not an RTOS.
+///
+/// Callbacks are never run from an interrupt handler: they are dispatched
+/// from [`SoftTimers::poll`], in the context that calls it (e.g. the main
+/// loop). A callback therefore runs late if `poll` is not called often
+/// enough, and a slow callback delays all others.
+///
+/// Example:
+/// ```
+/// fn blink() { /* ... */ }
+/// fn report() { /* ... */ }
+///
+/// let mut timers = hal::tmr::SoftTimers::<4>::new();
+/// timers.every(&mono, 500, blink).unwrap();
+/// let id = timers.after(&mono, 10_000, report).unwrap();
+/// loop {
+///     timers.poll(&mono);
+/// }
+/// ```
+pub struct SoftTimers<const N: usize> {
+    timers: [Option<SoftTimer>; N],
+}
+
+impl<const N: usize> Default for SoftTimers<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> SoftTimers<N> {
+    /// Create a new set of software timers with no callbacks registered.
+    pub const fn new() -> Self {
+        Self { timers: [None; N] }
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _add(&mut self, timer: SoftTimer) -> Option<usize> {
+        let id = self.timers.iter().position(Option::is_none)?;
+        self.timers[id] = Some(timer);
+        Some(id)
+    }
+
+    /// Register a callback to run every `period_ms` milliseconds, starting
+    /// `period_ms` milliseconds from now. Returns the ID of the timer, or
+    /// [`None`] if all `N` timers are in use.
+    ///
+    /// If [`SoftTimers::poll`] falls behind by more than a whole period,
+    /// the missed runs are skipped rather than run back to back.
+    ///
+    /// ## Panics
+    /// Panics if `period_ms` is `0`.
+    pub fn every<TMR: MonotonicTimer>(
+        &mut self,
+        mono: &Monotonic<TMR>,
+        period_ms: u64,
+        callback: fn(),
+    ) -> Option<usize> {
+        assert!(period_ms > 0, "Period must not be zero");
+        self._add(SoftTimer {
+            callback,
+            deadline_ms: mono.now_ms() + period_ms,
+            period_ms,
+        })
+    }
+
+    /// Register a callback to run once, `delay_ms` milliseconds from now.
+    /// Returns the ID of the timer, or [`None`] if all `N` timers are in
+    /// use. The timer is freed after the callback runs.
+    pub fn after<TMR: MonotonicTimer>(
+        &mut self,
+        mono: &Monotonic<TMR>,
+        delay_ms: u64,
+        callback: fn(),
+    ) -> Option<usize> {
+        self._add(SoftTimer {
+            callback,
+            deadline_ms: mono.now_ms() + delay_ms,
+            period_ms: 0,
+        })
+    }
+
+    /// Remove a timer so that its callback no longer runs. Returns [`true`]
+    /// if the timer was registered.
+    pub fn cancel(&mut self, id: usize) -> bool {
+        match self.timers.get_mut(id) {
+            Some(timer) => timer.take().is_some(),
+            None => false,
+        }
+    }
+
+    /// Run the callbacks of all timers that are due, and returns the number
+    /// of callbacks run. This should be called regularly, e.g. from the main
+    /// loop.
+    pub fn poll<TMR: MonotonicTimer>(&mut self, mono: &Monotonic<TMR>) -> usize {
+        let now_ms = mono.now_ms();
+        let mut count = 0;
+        for slot in self.timers.iter_mut() {
+            let Some(timer) = slot else {
+                continue;
+            };
+            if timer.deadline_ms > now_ms {
+                continue;
+            }
+            let callback = timer.callback;
+            if timer.period_ms == 0 {
+                *slot = None;
+            } else {
+                timer.deadline_ms += timer.period_ms;
+                // Skip missed runs if polling fell behind
+                if timer.deadline_ms <= now_ms {
+                    timer.deadline_ms = now_ms + timer.period_ms;
+                }
+            }
+            callback();
+            count += 1;
+        }
+        count
+    }
+}
@@ -8,6 +8,28 @@ use rand_core::CryptoRng;
 use rand_core::RngCore;
 #[cfg(feature = "rand")]
 use rand_core::impls::{fill_bytes_via_next, next_u64_via_u32};
+#[cfg(feature = "rand")]
+use rand_core::{TryCryptoRng, TryRngCore};
+
+/// Number of times the TRNG status is polled by [`Trng::try_gen_u32`] before
+/// giving up.
+pub const READY_TIMEOUT_POLLS: u32 = 100_000;
+
+/// TRNG errors.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TrngError {
+    /// The TRNG did not produce a random number in time.
+    Timeout,
+}
+
+impl core::fmt::Display for TrngError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TrngError::Timeout => f.write_str("TRNG timed out"),
+        }
+    }
+}
 
 /// # True Random Number Generator (TRNG) Peripheral
 ///
@@ -32,6 +54,46 @@ impl Trng {
         Self { trng }
     }
 
+    /// Gate the TRNG clock to stop the entropy source, and return the TRNG
+    /// peripheral from the PAC. The TRNG can be enabled again by passing the
+    /// returned peripheral to [`Trng::new`].
+    ///
+    /// Note: After the TRNG is enabled again, the entropy source has to
+    /// collect fresh entropy before the next number is ready. [`Trng::gen_u32`]
+    /// waits for it, while [`Trng::try_gen_u32`] reports a
+    /// [`TrngError::Timeout`] if it takes too long.
+    ///
+    /// Example:
+    /// ```
+    /// let trng = Trng::new(p.trng, &mut gcr.reg);
+    /// let nonce = trng.gen_block();
+    /// // Stop the TRNG until it is needed again
+    /// let pac_trng = trng.disable(&mut gcr.reg);
+    /// // ...
+    /// let trng = Trng::new(pac_trng, &mut gcr.reg);
+    /// ```
+    pub fn disable(self, reg: &mut crate::gcr::GcrRegisters) -> crate::pac::Trng {
+        self.release(reg)
+    }
+
+    /// Gate the TRNG clock and return the TRNG peripheral from the PAC. This
+    /// is the same as [`Trng::disable`], under the name used by the other
+    /// HAL peripherals.
+    pub fn release(self, reg: &mut crate::gcr::GcrRegisters) -> crate::pac::Trng {
+        use crate::gcr::ClockForPeripheral;
+        unsafe {
+            self.trng.disable_clock(&mut reg.gcr);
+        }
+        self.trng
+    }
+
+    /// Returns the raw value of the TRNG status register. Bit 0 is set when
+    /// a random number is ready to be read. The TRNG of the MAX78000 has no
+    /// health test status bits, so all other bits are reserved.
+    pub fn status(&self) -> u32 {
+        self.trng.status().read().bits()
+    }
+
     /// Check if the TRNG peripheral is ready to generate random numbers.
     #[doc(hidden)]
     #[inline(always)]
@@ -45,6 +107,34 @@ impl Trng {
         while !self._is_ready() {}
         self.trng.data().read().bits() as u32
     }
+
+    /// Generate a random 128-bit block, e.g. for use as an AES IV or nonce.
+    /// This consumes four TRNG words, stored in little-endian byte order.
+    pub fn gen_block(&self) -> [u8; 16] {
+        let mut block = [0; 16];
+        for chunk in block.chunks_exact_mut(4) {
+            chunk.copy_from_slice(&self.gen_u32().to_le_bytes());
+        }
+        block
+    }
+
+    /// Generate a random 32-bit number, giving up if the TRNG is not ready
+    /// after [`READY_TIMEOUT_POLLS`] polls.
+    pub fn try_gen_u32(&self) -> Result<u32, TrngError> {
+        for _ in 0..READY_TIMEOUT_POLLS {
+            if self._is_ready() {
+                return Ok(self.trng.data().read().bits());
+            }
+        }
+        Err(TrngError::Timeout)
+    }
+
+    /// Returns a fallible view of the TRNG, which reports a stalled TRNG as
+    /// an error instead of waiting forever.
+    #[cfg(feature = "rand")]
+    pub fn fallible(&mut self) -> FallibleTrng<'_> {
+        FallibleTrng { trng: self }
+    }
 }
 
 /// Enhanced functionality for the TRNG peripheral using the [`rand`] crate.
@@ -63,6 +153,24 @@ impl Trng {
 /// let mut buffer = [0u8; 16];
 /// trng.fill_bytes(&mut buffer);
 /// ```
+///
+/// Since `rand_core` 0.9 is used, the [`Trng`] also works directly with the
+/// convenience methods of `rand::Rng` from `rand` 0.9 (which implements
+/// `Rng` for every [`RngCore`]), without any wrapper:
+/// ```
+/// use rand::Rng;
+/// use rand::seq::SliceRandom;
+///
+/// let mut trng = Trng::new(p.trng, &mut gcr.reg);
+/// let value: u32 = trng.random();
+/// let die = trng.random_range(1..=6);
+/// let coin = trng.random_bool(0.5);
+/// let mut deck = [0u8, 1, 2, 3, 4, 5];
+/// deck.shuffle(&mut trng);
+/// ```
+/// Note: `rand` 0.9 renamed `gen` and `gen_range` to `random` and
+/// `random_range`. `rand` 0.8 depends on `rand_core` 0.6, so it is not
+/// compatible with this implementation.
 #[cfg(feature = "rand")]
 impl RngCore for Trng {
     #[inline(always)]
@@ -83,3 +191,49 @@ impl RngCore for Trng {
 
 #[cfg(feature = "rand")]
 impl CryptoRng for Trng {}
+
+/// Fallible view of the TRNG peripheral implementing [`TryRngCore`].
+///
+/// [`Trng`] implements the infallible [`RngCore`], which `rand_core`
+/// already extends to [`TryRngCore`] with an infallible error. This wrapper
+/// uses [`Trng::try_gen_u32`] instead, so a stalled TRNG is reported as a
+/// [`TrngError`].
+///
+/// Example:
+/// ```
+/// let mut trng = Trng::new(p.trng, &mut gcr.reg);
+/// let mut key = [0u8; 32];
+/// trng.fallible().try_fill_bytes(&mut key)?;
+/// ```
+#[cfg(feature = "rand")]
+pub struct FallibleTrng<'a> {
+    trng: &'a mut Trng,
+}
+
+#[cfg(feature = "rand")]
+impl TryRngCore for FallibleTrng<'_> {
+    type Error = TrngError;
+
+    #[inline(always)]
+    fn try_next_u32(&mut self) -> Result<u32, Self::Error> {
+        self.trng.try_gen_u32()
+    }
+
+    #[inline(always)]
+    fn try_next_u64(&mut self) -> Result<u64, Self::Error> {
+        let low = self.try_next_u32()? as u64;
+        let high = self.try_next_u32()? as u64;
+        Ok((high << 32) | low)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Self::Error> {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.try_next_u32()?.to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rand")]
+impl TryCryptoRng for FallibleTrng<'_> {}
@@ -3,10 +3,11 @@ use core::marker::PhantomData;
 use core::ops::Deref;
 
 use crate::gcr::{
-    clocks::{Clock, InternalBaudRateOscillator, PeripheralClock},
+    clocks::{Clock, InternalBaudRateOscillator, OscillatorSource, PeripheralClock},
     ClockForPeripheral,
 };
 use crate::gpio::{Af1, Pin};
+use embedded_hal::delay::DelayNs;
 use embedded_hal_nb::{nb, serial};
 use paste::paste;
 
@@ -16,6 +17,7 @@ enum UartClockSource {
 }
 
 /// Number of data bits in a UART frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DataBits {
     /// 5 data bits.
     Five,
@@ -28,6 +30,7 @@ pub enum DataBits {
 }
 
 /// Number of stop bits in a UART frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum StopBits {
     /// 1 stop bit.
     One,
@@ -37,6 +40,7 @@ pub enum StopBits {
 }
 
 /// Parity bit configuration for a UART frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ParityBit {
     /// Parity bit is not used.
     None,
@@ -64,6 +68,137 @@ pub enum ParityBit {
     MarkOne,
 }
 
+/// Frame format and baud rate of a built UART peripheral, see
+/// [`BuiltUartPeripheral::config`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UartConfig {
+    /// Actual baud rate (bits per second), which may differ slightly from
+    /// the requested baud rate due to integer division of the clock.
+    pub baud: u32,
+    /// Number of data bits per frame.
+    pub data_bits: DataBits,
+    /// Number of stop bits per frame.
+    pub stop_bits: StopBits,
+    /// Parity bit configuration.
+    pub parity: ParityBit,
+}
+
+/// Receive errors reported by the [`embedded_io::Read`] and
+/// [`embedded_io::BufRead`] implementations. The [`embedded_hal_nb::serial`]
+/// implementation reports the same errors as [`serial::ErrorKind`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UartError {
+    /// A frame was received without a valid stop bit.
+    Framing,
+    /// A frame was received with an incorrect parity bit.
+    Parity,
+    /// The receive FIFO was full when a frame was received, so data was
+    /// lost.
+    Overrun,
+}
+
+impl serial::Error for UartError {
+    fn kind(&self) -> serial::ErrorKind {
+        match self {
+            UartError::Framing => serial::ErrorKind::FrameFormat,
+            UartError::Parity => serial::ErrorKind::Parity,
+            UartError::Overrun => serial::ErrorKind::Overrun,
+        }
+    }
+}
+
+impl embedded_io::Error for UartError {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            UartError::Framing | UartError::Parity => embedded_io::ErrorKind::InvalidData,
+            UartError::Overrun => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+/// Error returned by [`compute_clkdiv`] when a baud rate cannot be generated
+/// from a clock source.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BaudError {
+    /// The baud rate is zero, or faster than the clock source.
+    TooFast,
+    /// The baud rate needs a divisor larger than [`MAX_CLKDIV`].
+    TooSlow,
+    /// The closest achievable baud rate is off by more than the tolerance
+    /// ([`BAUD_TOLERANCE_PERMILLE`] by default).
+    Inaccurate,
+}
+
+/// Error returned by [`BuiltUartPeripheral::read_until`] when the buffer
+/// filled up before the delimiter was received.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OverflowError;
+
+/// Error returned by [`BuiltUartPeripheral::read_exact_timeout`] when no
+/// byte was received within the timeout.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ReadTimeout {
+    /// Number of bytes read into the start of the buffer before the timeout.
+    pub read: usize,
+}
+
+/// Snapshot of the UART status flags, see [`BuiltUartPeripheral::status`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UartStatus {
+    /// The transmitter is shifting out a frame.
+    pub tx_busy: bool,
+    /// The receiver is receiving a frame.
+    pub rx_busy: bool,
+    /// The transmit FIFO is empty.
+    pub tx_empty: bool,
+    /// The transmit FIFO is full.
+    pub tx_full: bool,
+    /// The receive FIFO is empty.
+    pub rx_empty: bool,
+    /// The receive FIFO is full.
+    pub rx_full: bool,
+    /// Number of bytes in the transmit FIFO (0 to [`FIFO_DEPTH`]).
+    pub tx_level: u8,
+    /// Number of bytes in the receive FIFO (0 to [`FIFO_DEPTH`]).
+    pub rx_level: u8,
+    /// Sampled level of the CTS input ([`true`] if high). Only meaningful
+    /// when a CTS pin is in use.
+    pub cts: bool,
+}
+
+/// Snapshot of the UART receive error flags, see
+/// [`BuiltUartPeripheral::error_flags`].
+///
+/// The flags are sticky: they stay set until cleared with
+/// [`BuiltUartPeripheral::clear_error_flags`]. Reading a byte with
+/// [`BuiltUartPeripheral::read_byte`] does not clear them, but the
+/// [`embedded_io::Read`], [`embedded_io::BufRead`] and
+/// [`embedded_hal_nb::serial::Read`] implementations clear them when they
+/// report an error.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UartErrors {
+    /// A frame was received without a valid stop bit (e.g. due to a baud
+    /// rate mismatch, noise, or a break condition).
+    pub frame: bool,
+    /// A frame was received with a parity bit that does not match the
+    /// configured parity. Never set when parity is disabled.
+    pub parity: bool,
+    /// A frame was received while the receive FIFO was full, so it was lost.
+    pub overrun: bool,
+}
+
+impl UartErrors {
+    /// Returns [`true`] if any error flag is set.
+    pub fn any(&self) -> bool {
+        self.frame || self.parity || self.overrun
+    }
+}
+
 #[doc(hidden)]
 pub mod marker {
     /// Marker traits for the build state of the UART peripheral.
@@ -108,7 +243,7 @@ pub mod marker {
 ///     pins.p0_0.into_af1(),   // RX pin
 ///     pins.p0_1.into_af1()    // TX pin
 /// )
-///     .clock_pclk(&clks.pclk) // or clocks_ibro(&ibro)
+///     .clock_pclk(&clks.pclk) // or clock_ibro(&ibro), or clock_ibro_default()
 ///     .baud(115200)
 ///     .data_bits(hal::uart::DataBits::Eight)
 ///     .stop_bits(hal::uart::StopBits::One)
@@ -129,6 +264,7 @@ pub struct UartPeripheral<STATE: marker::UartState, CLOCK, UART, RX, TX, CTS, RT
     clk_src: Option<UartClockSource>,
     clk_src_freq: Option<u32>,
     baud: u32,
+    baud_tolerance: u32,
     data_bits: DataBits,
     stop_bits: StopBits,
     parity: ParityBit,
@@ -140,6 +276,11 @@ pub struct BuiltUartPeripheral<UART, RX, TX, CTS, RTS> {
     _tx_pin: TX,
     _cts_pin: CTS,
     _rts_pin: RTS,
+    clk_src_freq: u32,
+    tx_pending: &'static [u8],
+    rx_buf: [u8; BUF_READ_SIZE],
+    rx_start: usize,
+    rx_end: usize,
 }
 
 // TODO
@@ -165,12 +306,97 @@ pub trait TxPin<UART>: crate::Sealed {}
 // pub trait CtsPin<UART>: crate::Sealed {}
 // pub trait RtsPin<UART>: crate::Sealed {}
 
+/// Depth (in bytes) of the UART transmit and receive FIFOs.
+pub const FIFO_DEPTH: u8 = 8;
+
+/// Largest baud rate divisor supported by the UART (20 bits).
+pub const MAX_CLKDIV: u32 = 0xF_FFFF;
+
+/// Largest baud rate error accepted by [`compute_clkdiv`], in parts per
+/// thousand. Both ends of a UART link sample in the middle of each bit, so
+/// their combined error must stay well below half a bit over a whole frame.
+pub const BAUD_TOLERANCE_PERMILLE: u32 = 20;
+
+/// Computes the baud rate divisor for a clock source frequency, rounding to
+/// the closest achievable baud rate. Returns the divisor and the achieved
+/// baud rate (`src_freq / clkdiv`).
+///
+/// This is used by [`UartPeripheral::build`], and can be used beforehand to
+/// check whether a clock source can generate a baud rate. Slow clock
+/// sources (such as the 32.768 kHz ERTCO) can only generate low baud rates:
+/// 9600 baud needs a divisor of 3.4, so the closest rate is off by 14%.
+///
+/// Example:
+/// ```
+/// // IBRO at 7.3728 MHz generates 115200 baud exactly
+/// assert_eq!(hal::uart::compute_clkdiv(7_372_800, 115_200), Ok((64, 115_200)));
+/// assert_eq!(
+///     hal::uart::compute_clkdiv(32_768, 9600),
+///     Err(hal::uart::BaudError::Inaccurate)
+/// );
+/// ```
+pub const fn compute_clkdiv(src_freq: u32, baud: u32) -> Result<(u32, u32), BaudError> {
+    compute_clkdiv_with_tolerance(src_freq, baud, BAUD_TOLERANCE_PERMILLE)
+}
+
+/// Computes the baud rate divisor like [`compute_clkdiv`], but with a
+/// custom tolerance for the baud rate error, in parts per thousand.
+pub const fn compute_clkdiv_with_tolerance(
+    src_freq: u32,
+    baud: u32,
+    tolerance_permille: u32,
+) -> Result<(u32, u32), BaudError> {
+    if baud == 0 || baud > src_freq {
+        return Err(BaudError::TooFast);
+    }
+    // Round to the closest divisor
+    let clkdiv = ((src_freq as u64 + baud as u64 / 2) / baud as u64) as u32;
+    if clkdiv > MAX_CLKDIV {
+        return Err(BaudError::TooSlow);
+    }
+    let actual = src_freq / clkdiv;
+    let error = actual.abs_diff(baud) as u64 * 1000;
+    if error > tolerance_permille as u64 * baud as u64 {
+        return Err(BaudError::Inaccurate);
+    }
+    Ok((clkdiv, actual))
+}
+
+/// Size (in bytes) of the internal receive buffer used by the
+/// [`embedded_io::BufRead`] implementation. The buffer holds at most one
+/// full receive FIFO, and its size is fixed to [`FIFO_DEPTH`].
+///
+/// Buffered bytes are returned first by the [`embedded_io::Read`] and
+/// [`embedded_hal_nb::serial::Read`] implementations, but are skipped by
+/// [`BuiltUartPeripheral::read_byte`] and [`BuiltUartPeripheral::read_bytes`].
+pub const BUF_READ_SIZE: usize = FIFO_DEPTH as usize;
+
 // All UART peripherals are derived from the same register block
 type UartRegisterBlock = crate::pac::uart0::RegisterBlock;
 
+/// Interrupt vector of a UART peripheral.
+#[doc(hidden)]
+pub trait UartInterrupt {
+    const INTERRUPT: crate::Interrupt;
+}
+
+/// Encoding of the IBRO in the baud clock source field of a UART peripheral.
+/// The low-power UART (UART3) has a different set of clock sources.
+#[doc(hidden)]
+pub trait UartClockSelect {
+    const IBRO_BCLKSRC: u8;
+}
+
+/// UART peripherals that can be clocked from the PCLK. The low-power UART
+/// (UART3) can only be clocked from the IBRO or ERTCO.
+#[doc(hidden)]
+pub trait UartPclkSource {}
+
 macro_rules! uart {
     (
         $uart:ident,
+        reg: $reg:ident,
+        ibro_bclksrc: $ibro_bclksrc:expr,
         rx: $rx_pin:ty,
         tx: $tx_pin:ty,
         cts: $cts_pin:ty,
@@ -179,6 +405,14 @@ macro_rules! uart {
         paste! {
             use crate::pac::$uart;
 
+            impl UartInterrupt for $uart {
+                const INTERRUPT: crate::Interrupt = crate::Interrupt::[<$uart:upper>];
+            }
+
+            impl UartClockSelect for $uart {
+                const IBRO_BCLKSRC: u8 = $ibro_bclksrc;
+            }
+
             impl crate::Sealed for $rx_pin {}
             impl RxPin<$uart> for $rx_pin {}
 
@@ -200,14 +434,19 @@ macro_rules! uart {
                 #[doc = "Construct a new "]
                 #[doc = stringify!([<$uart:upper>])]
                 #[doc = " peripheral."]
+                ///
+                /// The peripheral clock is enabled if it is not already, so
+                /// the peripheral can be safely reconstructed (e.g. after a
+                /// warm reset) without disabling its clock first. The UART is
+                /// fully reconfigured when it is built.
                 pub fn [<$uart:lower>](
                     uart: $uart,
                     reg: &mut crate::gcr::GcrRegisters,
                     rx_pin: $rx_pin,
                     tx_pin: $tx_pin
                 ) -> UartPeripheral<marker::NotBuilt, marker::NotClockSet, $uart, $rx_pin, $tx_pin, (), ()> {
-                    // Enable the UART peripheral clock
-                    unsafe { uart.enable_clock(&mut reg.gcr); }
+                    // Enable the UART peripheral clock (no-op if already enabled)
+                    unsafe { uart.enable_clock(&mut reg.$reg); }
                     UartPeripheral {
                         _state: PhantomData,
                         _clock: PhantomData,
@@ -219,17 +458,34 @@ macro_rules! uart {
                         clk_src: None,
                         clk_src_freq: None,
                         baud: 115200,
+                        baud_tolerance: BAUD_TOLERANCE_PERMILLE,
                         data_bits: DataBits::Eight,
                         stop_bits: StopBits::One,
                         parity: ParityBit::None,
                     }
                 }
             }
+
+            impl<RX, TX, CTS, RTS> BuiltUartPeripheral<$uart, RX, TX, CTS, RTS> {
+                /// Wait until all bytes in the transmit FIFO have been sent,
+                /// gate the UART clock, and return the UART peripheral from
+                /// the PAC and the pins. All UART interrupts are disabled, and
+                /// the pins are left in their alternate function.
+                pub fn release(self, reg: &mut crate::gcr::GcrRegisters) -> ($uart, RX, TX, CTS, RTS) {
+                    self.flush_tx();
+                    while self.uart.status().read().tx_busy().bit_is_set() {}
+                    self.uart.int_en().write(|w| unsafe { w.bits(0) });
+                    unsafe { self.uart.disable_clock(&mut reg.$reg); }
+                    (self.uart, self._rx_pin, self._tx_pin, self._cts_pin, self._rts_pin)
+                }
+            }
         }
     };
 }
 
 uart! {Uart0,
+    reg: gcr,
+    ibro_bclksrc: 2,
     rx: Pin<0, 0, Af1>,
     tx: Pin<0, 1, Af1>,
     cts: (),
@@ -237,6 +493,8 @@ uart! {Uart0,
 }
 
 uart! {Uart1,
+    reg: gcr,
+    ibro_bclksrc: 2,
     rx: Pin<0, 12, Af1>,
     tx: Pin<0, 13, Af1>,
     cts: (),
@@ -244,17 +502,40 @@ uart! {Uart1,
 }
 
 uart! {Uart2,
+    reg: gcr,
+    ibro_bclksrc: 2,
     rx: Pin<1, 0, Af1>,
     tx: Pin<1, 1, Af1>,
     cts: (),
     rts: (),
 }
 
+// The low-power UART (LPUART0) selects the IBRO with 0 and the ERTCO with 1
+uart! {Uart3,
+    reg: lpgcr,
+    ibro_bclksrc: 0,
+    rx: Pin<2, 6, Af1>,
+    tx: Pin<2, 7, Af1>,
+    cts: (),
+    rts: (),
+}
+
+impl UartPclkSource for Uart0 {}
+impl UartPclkSource for Uart1 {}
+impl UartPclkSource for Uart2 {}
+
 /// # Clock Methods
 /// You must set the clock source for the UART peripheral after using a
 /// constructor and before building the peripheral.
+///
+/// UART0 to UART2 can be clocked from the PCLK or the IBRO. The low-power
+/// UART3 can only be clocked from the IBRO (or the ERTCO, which the HAL does
+/// not support yet), so it keeps running in low-power modes where the PCLK
+/// is stopped.
 impl<UART, RX, TX, CTS, RTS>
     UartPeripheral<marker::NotBuilt, marker::NotClockSet, UART, RX, TX, CTS, RTS>
+where
+    UART: UartPclkSource,
 {
     /// Set the clock source for the UART peripheral to the PCLK.
     pub fn clock_pclk(
@@ -272,12 +553,17 @@ impl<UART, RX, TX, CTS, RTS>
             clk_src: Some(UartClockSource::Pclk),
             clk_src_freq: Some(clock.frequency),
             baud: self.baud,
+            baud_tolerance: self.baud_tolerance,
             data_bits: self.data_bits,
             stop_bits: self.stop_bits,
             parity: self.parity,
         }
     }
+}
 
+impl<UART, RX, TX, CTS, RTS>
+    UartPeripheral<marker::NotBuilt, marker::NotClockSet, UART, RX, TX, CTS, RTS>
+{
     /// Set the clock source for the UART peripheral to the IBRO.
     pub fn clock_ibro(
         self,
@@ -294,6 +580,37 @@ impl<UART, RX, TX, CTS, RTS>
             clk_src: Some(UartClockSource::Ibro),
             clk_src_freq: Some(clock.frequency),
             baud: self.baud,
+            baud_tolerance: self.baud_tolerance,
+            data_bits: self.data_bits,
+            stop_bits: self.stop_bits,
+            parity: self.parity,
+        }
+    }
+
+    /// Set the clock source for the UART peripheral to the IBRO, without
+    /// requiring an IBRO [`Clock`].
+    ///
+    /// The IBRO is always enabled, so this only waits until it is ready and
+    /// uses its nominal frequency (7.3728 MHz). Use [`Self::clock_ibro()`]
+    /// instead if you manage the oscillators yourself.
+    pub fn clock_ibro_default(
+        self,
+    ) -> UartPeripheral<marker::NotBuilt, marker::ClockSet, UART, RX, TX, CTS, RTS> {
+        // Safety: Concurrent read access to the GCR clock control register is safe
+        let gcr = unsafe { &*crate::pac::Gcr::ptr() };
+        while gcr.clkctrl().read().ibro_rdy().bit_is_clear() {}
+        UartPeripheral {
+            _state: PhantomData,
+            _clock: PhantomData,
+            uart: self.uart,
+            _rx_pin: self._rx_pin,
+            _tx_pin: self._tx_pin,
+            _cts_pin: self._cts_pin,
+            _rts_pin: self._rts_pin,
+            clk_src: Some(UartClockSource::Ibro),
+            clk_src_freq: Some(InternalBaudRateOscillator::BASE_FREQUENCY),
+            baud: self.baud,
+            baud_tolerance: self.baud_tolerance,
             data_bits: self.data_bits,
             stop_bits: self.stop_bits,
             parity: self.parity,
@@ -301,6 +618,32 @@ impl<UART, RX, TX, CTS, RTS>
     }
 }
 
+impl<UART, RX, TX, CTS, RTS>
+    UartPeripheral<marker::NotBuilt, marker::ClockSet, UART, RX, TX, CTS, RTS>
+where
+    UART: Deref<Target = UartRegisterBlock>,
+{
+    /// Set the baud rate like [`UartPeripheral::baud`], but first check
+    /// that the clock source can generate it with an error of at most
+    /// `tolerance_permille` parts per thousand. The clock source must be set
+    /// first. The tolerance is also used by [`UartPeripheral::try_build`].
+    ///
+    /// Example:
+    /// ```
+    /// // 250000 baud from the 7.3728 MHz IBRO is 1.7% off
+    /// let uart = UartPeripheral::uart0(p.uart0, &mut gcr.reg, rx_pin, tx_pin)
+    ///     .clock_ibro(&ibro)
+    ///     .try_baud(250_000, 20)?
+    ///     .build();
+    /// ```
+    pub fn try_baud(mut self, baud: u32, tolerance_permille: u32) -> Result<Self, BaudError> {
+        compute_clkdiv_with_tolerance(self.clk_src_freq.unwrap(), baud, tolerance_permille)?;
+        self.baud = baud;
+        self.baud_tolerance = tolerance_permille;
+        Ok(self)
+    }
+}
+
 /// # Builder Methods
 /// These methods are used to configure the UART peripheral before it is built
 /// to be used. Configure the peripheral by chaining these methods together,
@@ -371,18 +714,50 @@ where
 impl<UART, RX, TX, CTS, RTS>
     UartPeripheral<marker::NotBuilt, marker::ClockSet, UART, RX, TX, CTS, RTS>
 where
-    UART: Deref<Target = UartRegisterBlock>,
+    UART: Deref<Target = UartRegisterBlock> + UartClockSelect,
 {
     /// Apply all settings and configure the UART peripheral.
     /// This must be called before the UART peripheral can be used.
+    ///
+    /// This is a convenience wrapper around [`UartPeripheral::try_build`]
+    /// for baud rates that are known to be achievable, such as the default
+    /// 115200 baud from the PCLK or the IBRO.
+    ///
+    /// ## Panics
+    /// Panics if the baud rate cannot be generated from the clock source
+    /// (see [`compute_clkdiv`]). Use [`UartPeripheral::try_build`] to handle
+    /// this as an error instead.
     pub fn build(self) -> BuiltUartPeripheral<UART, RX, TX, CTS, RTS> {
+        self.try_build()
+            .expect("UART baud rate cannot be generated from the clock source")
+    }
+
+    /// Apply all settings and configure the UART peripheral, or return a
+    /// [`BaudError`] without touching the hardware if the baud rate cannot
+    /// be generated from the clock source (see [`compute_clkdiv`]). The
+    /// baud rate error may be at most [`BAUD_TOLERANCE_PERMILLE`], or the
+    /// tolerance given to [`UartPeripheral::try_baud`].
+    ///
+    /// Example:
+    /// ```
+    /// // 3 Mbaud is 23% off when divided from the 7.3728 MHz IBRO
+    /// let result = UartPeripheral::uart0(p.uart0, &mut gcr.reg, rx_pin, tx_pin)
+    ///     .clock_ibro(&ibro)
+    ///     .baud(3_000_000)
+    ///     .try_build();
+    /// assert_eq!(result.err(), Some(hal::uart::BaudError::Inaccurate));
+    /// ```
+    pub fn try_build(self) -> Result<BuiltUartPeripheral<UART, RX, TX, CTS, RTS>, BaudError> {
         // Configure the UART peripheral
         let clk_src_freq = self.clk_src_freq.unwrap();
+        let (clkdiv, _) =
+            compute_clkdiv_with_tolerance(clk_src_freq, self.baud, self.baud_tolerance)?;
         self.uart.ctrl().write(|w| {
             w.ucagm().set_bit();
             match self.clk_src {
                 Some(UartClockSource::Pclk) => w.bclksrc().peripheral_clock(),
-                Some(UartClockSource::Ibro) => w.bclksrc().clk2(),
+                // Safety: The IBRO encoding is a valid clock source for this UART
+                Some(UartClockSource::Ibro) => unsafe { w.bclksrc().bits(UART::IBRO_BCLKSRC) },
                 None => unreachable!("UART clock source not set"),
             };
             w.bclken().set_bit();
@@ -406,19 +781,23 @@ where
             return w;
         });
         // Set the baud rate
-        let clkdiv = clk_src_freq / self.baud;
         self.uart
             .clkdiv()
             .write(|w| unsafe { w.clkdiv().bits(clkdiv) });
         // Wait until baud clock is ready
         while self.uart.ctrl().read().bclkrdy().bit_is_clear() {}
-        BuiltUartPeripheral {
+        Ok(BuiltUartPeripheral {
             uart: self.uart,
             _rx_pin: self._rx_pin,
             _tx_pin: self._tx_pin,
             _cts_pin: self._cts_pin,
             _rts_pin: self._rts_pin,
-        }
+            clk_src_freq,
+            tx_pending: &[],
+            rx_buf: [0; BUF_READ_SIZE],
+            rx_start: 0,
+            rx_end: 0,
+        })
     }
 }
 
@@ -456,6 +835,28 @@ where
         Ok(self.uart.fifo().read().data().bits())
     }
 
+    /// Check the framing and parity error flags after reading, and clear
+    /// them. The flags are not tied to a particular byte, so an error is
+    /// reported for the read during which it was detected. Overruns are
+    /// checked before reading with [`Self::_take_rx_overrun`].
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _take_rx_error(&self) -> Result<(), UartError> {
+        let int_fl = self.uart.int_fl().read();
+        let error = if int_fl.rx_ferr().bit_is_set() {
+            UartError::Framing
+        } else if int_fl.rx_par().bit_is_set() {
+            UartError::Parity
+        } else {
+            return Ok(());
+        };
+        // Interrupt flags are write 1 to clear
+        self.uart
+            .int_fl()
+            .write(|w| w.rx_ferr().set_bit().rx_par().set_bit());
+        Err(error)
+    }
+
     #[doc(hidden)]
     #[inline(always)]
     fn _write_byte(&self, byte: u8) -> nb::Result<(), serial::ErrorKind> {
@@ -466,11 +867,48 @@ where
         Ok(())
     }
 
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _flush_tx(&self) -> nb::Result<(), serial::ErrorKind> {
+        if !self._is_tx_empty() {
+            return Err(nb::Error::WouldBlock);
+        }
+        Ok(())
+    }
+
+    /// Clear the receive FIFO overrun flag. Returns [`true`] if an overrun
+    /// occurred.
+    ///
+    /// The bytes still in the receive FIFO were received before the dropped
+    /// ones, so they are valid and are kept to be read after the overrun is
+    /// reported.
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _take_rx_overrun(&self) -> bool {
+        if self.uart.int_fl().read().rx_ov().bit_is_clear() {
+            return false;
+        }
+        // Interrupt flags are write 1 to clear
+        self.uart.int_fl().write(|w| w.rx_ov().set_bit());
+        true
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _pop_buffered(&mut self) -> Option<u8> {
+        if self.rx_start == self.rx_end {
+            return None;
+        }
+        let byte = self.rx_buf[self.rx_start];
+        self.rx_start += 1;
+        Some(byte)
+    }
+
     /// Flush the transmit buffer, ensuring that all bytes have been sent.
     /// This is a blocking operation.
     #[inline(always)]
     fn flush_tx(&self) {
-        while !self._is_tx_empty() {}
+        nb::block!(self._flush_tx()).unwrap()
     }
 
     /// Reads a single byte. This is a blocking operation.
@@ -492,13 +930,375 @@ where
         }
     }
 
+    /// Reads bytes to a buffer until `delim` is received, and returns the
+    /// number of bytes written to the buffer, not including the delimiter
+    /// (which is consumed but not stored). This is a blocking operation.
+    ///
+    /// Bytes buffered by [`embedded_io::BufRead`] are read first.
+    ///
+    /// A message of exactly `buffer.len()` bytes fits, since the delimiter
+    /// is not stored: once the buffer is full, one more byte is read, and
+    /// `Ok(buffer.len())` is returned if it is the delimiter. Otherwise
+    /// [`OverflowError`] is returned with the whole buffer filled. That
+    /// extra byte has then been consumed, and the rest of the message is
+    /// left to be read.
+    ///
+    /// Example:
+    /// ```
+    /// let mut buf = [0u8; 32];
+    /// match uart.read_until(b';', &mut buf) {
+    ///     Ok(len) => { /* command in buf[..len] */ }
+    ///     Err(OverflowError) => { /* command too long */ }
+    /// }
+    /// ```
+    pub fn read_until(&mut self, delim: u8, buffer: &mut [u8]) -> Result<usize, OverflowError> {
+        // Bytes buffered by BufRead are returned first
+        let mut next_byte = || self._pop_buffered().unwrap_or_else(|| self.read_byte());
+        for (count, slot) in buffer.iter_mut().enumerate() {
+            let byte = next_byte();
+            if byte == delim {
+                return Ok(count);
+            }
+            *slot = byte;
+        }
+        if next_byte() == delim {
+            return Ok(buffer.len());
+        }
+        Err(OverflowError)
+    }
+
+    /// Reads a line into a buffer like [`BuiltUartPeripheral::read_until`],
+    /// with `\n` as the delimiter. A `\r` before the `\n` is kept in the
+    /// buffer. This is a blocking operation.
+    pub fn read_line(&mut self, buffer: &mut [u8]) -> Result<usize, OverflowError> {
+        self.read_until(b'\n', buffer)
+    }
+
+    /// Reads bytes to a buffer like [`BuiltUartPeripheral::read_bytes`], but
+    /// gives up if no byte arrives for `timeout_us` microseconds. The timeout
+    /// restarts after every received byte, so it bounds the gap between
+    /// bytes rather than the whole read. This is a blocking operation.
+    ///
+    /// On timeout, the bytes received so far are left at the start of the
+    /// buffer and their count is returned in [`ReadTimeout::read`].
+    ///
+    /// The timeout is measured by waiting 1 µs between polls with `delay`,
+    /// so the actual timeout is slightly longer than requested.
+    ///
+    /// Example:
+    /// ```
+    /// let mut frame = [0u8; 8];
+    /// match uart.read_exact_timeout(&mut frame, &mut delay, 10_000) {
+    ///     Ok(()) => { /* full frame */ }
+    ///     Err(e) => { /* truncated frame of e.read bytes */ }
+    /// }
+    /// ```
+    pub fn read_exact_timeout(
+        &mut self,
+        buffer: &mut [u8],
+        delay: &mut impl DelayNs,
+        timeout_us: u32,
+    ) -> Result<(), ReadTimeout> {
+        for (read, byte) in buffer.iter_mut().enumerate() {
+            // Bytes buffered by BufRead are returned first
+            if let Some(buffered) = self._pop_buffered() {
+                *byte = buffered;
+                continue;
+            }
+            let mut waited = 0;
+            loop {
+                match self._read_byte() {
+                    Ok(received) => {
+                        *byte = received;
+                        break;
+                    }
+                    Err(_) if waited >= timeout_us => return Err(ReadTimeout { read }),
+                    Err(_) => {
+                        delay.delay_us(1);
+                        waited += 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Write bytes from a buffer (blocking). The entire buffer will be written
     /// to the UART peripheral. This is a blocking operation.
     pub fn write_bytes(&self, buffer: &[u8]) {
-        for byte in buffer {
-            self.write_byte(*byte);
+        let mut remaining = buffer;
+        while !remaining.is_empty() {
+            let count = self.fill_tx_fifo(remaining);
+            remaining = &remaining[count..];
+        }
+    }
+
+    /// Writes as many bytes from a buffer as currently fit in the transmit
+    /// FIFO, and returns the number of bytes written. This is a non-blocking
+    /// operation.
+    ///
+    /// The free space in the FIFO is checked once, so at most
+    /// [`FIFO_DEPTH`] bytes are written without re-reading the status
+    /// register.
+    pub fn fill_tx_fifo(&self, buffer: &[u8]) -> usize {
+        let level = self.uart.status().read().tx_lvl().bits();
+        let count = (FIFO_DEPTH.saturating_sub(level) as usize).min(buffer.len());
+        for byte in &buffer[..count] {
+            self.uart.fifo().write(|w| unsafe { w.data().bits(*byte) });
+        }
+        count
+    }
+
+    /// Start transmitting a buffer in the background. The transmit FIFO is
+    /// filled immediately, and refilled from the TX half empty interrupt
+    /// until the whole buffer has been queued. This is a non-blocking
+    /// operation.
+    ///
+    /// The UART interrupt must be unmasked in the NVIC (see
+    /// [`BuiltUartPeripheral::interrupt`]) and its handler must call
+    /// [`BuiltUartPeripheral::on_tx_interrupt`].
+    ///
+    /// The buffer must be `'static` since the interrupt handler keeps reading
+    /// from it after this function returns.
+    ///
+    /// ## Panics
+    /// Panics if a previous transfer is still in progress (see
+    /// [`BuiltUartPeripheral::tx_busy`]).
+    ///
+    /// Example:
+    /// ```
+    /// static MESSAGE: &[u8] = b"Hello, world!\r\n";
+    /// uart.start_tx(MESSAGE);
+    ///
+    /// #[interrupt]
+    /// fn UART0() {
+    ///     // The UART must be shared with the handler, e.g. in a Mutex
+    ///     uart.on_tx_interrupt();
+    /// }
+    /// ```
+    pub fn start_tx(&mut self, buffer: &'static [u8]) {
+        assert!(!self.tx_busy(), "UART transfer already in progress");
+        let count = self.fill_tx_fifo(buffer);
+        self.tx_pending = &buffer[count..];
+        if !self.tx_pending.is_empty() {
+            // Interrupt flags are write 1 to clear
+            self.uart.int_fl().write(|w| w.tx_he().set_bit());
+            self.uart.int_en().modify(|_, w| w.tx_he().set_bit());
+        }
+    }
+
+    /// Handle the TX half empty interrupt of a transfer started with
+    /// [`BuiltUartPeripheral::start_tx`] by refilling the transmit FIFO. The
+    /// interrupt is disabled once the whole buffer has been queued. This must
+    /// be called from the interrupt handler of the UART peripheral.
+    pub fn on_tx_interrupt(&mut self) {
+        if self.uart.int_fl().read().tx_he().bit_is_clear() {
+            return;
+        }
+        // Interrupt flags are write 1 to clear
+        self.uart.int_fl().write(|w| w.tx_he().set_bit());
+        let count = self.fill_tx_fifo(self.tx_pending);
+        self.tx_pending = &self.tx_pending[count..];
+        if self.tx_pending.is_empty() {
+            self.uart.int_en().modify(|_, w| w.tx_he().clear_bit());
+        }
+    }
+
+    /// Returns [`true`] if a transfer started with
+    /// [`BuiltUartPeripheral::start_tx`] still has bytes waiting to be queued.
+    ///
+    /// Note: Once this returns [`false`], the last bytes may still be in the
+    /// transmit FIFO. Use [`embedded_io::Write::flush`] to wait until they
+    /// have been sent.
+    pub fn tx_busy(&self) -> bool {
+        !self.tx_pending.is_empty()
+    }
+
+    /// Returns the number of bytes waiting in the receive FIFO, from 0 to
+    /// [`FIFO_DEPTH`]. At least this many bytes can be read with
+    /// [`BuiltUartPeripheral::read_byte`] without blocking.
+    ///
+    /// Note: Bytes already buffered by [`embedded_io::BufRead`] are not
+    /// included.
+    pub fn rx_level(&self) -> u8 {
+        self.uart.status().read().rx_lvl().bits()
+    }
+
+    /// Returns the baud rate actually generated by the hardware, which can
+    /// differ from the requested baud rate since the clock source is divided
+    /// by an integer (see [`compute_clkdiv`]).
+    pub fn baud_actual(&self) -> u32 {
+        let clkdiv = self.uart.clkdiv().read().clkdiv().bits();
+        self.clk_src_freq / clkdiv.max(1)
+    }
+
+    /// Returns the current baud rate and frame format, decoded from the
+    /// hardware registers.
+    ///
+    /// Note: [`ParityBit::Even`] and [`ParityBit::SpaceZero`] share the same
+    /// register encoding, so both are reported as [`ParityBit::Even`].
+    pub fn config(&self) -> UartConfig {
+        let ctrl = self.uart.ctrl().read();
+        let clkdiv = self.uart.clkdiv().read().clkdiv().bits();
+        let data_bits = match ctrl.char_size().bits() {
+            0 => DataBits::Five,
+            1 => DataBits::Six,
+            2 => DataBits::Seven,
+            _ => DataBits::Eight,
+        };
+        let stop_bits = match ctrl.stopbits().bit_is_set() {
+            false => StopBits::One,
+            true => StopBits::More,
+        };
+        let parity = if ctrl.par_en().bit_is_clear() {
+            ParityBit::None
+        } else if ctrl.par_md().bit_is_set() {
+            ParityBit::MarkOne
+        } else if ctrl.par_eo().bit_is_set() {
+            ParityBit::Odd
+        } else {
+            ParityBit::Even
+        };
+        UartConfig {
+            baud: self.clk_src_freq / clkdiv.max(1),
+            data_bits,
+            stop_bits,
+            parity,
         }
     }
+
+    /// Changes the parity of the UART peripheral in place, keeping the baud
+    /// rate, the other frame settings and the pins.
+    ///
+    /// This waits until the transmitter and receiver are idle, then stops
+    /// the baud clock while the parity is changed, so a frame is never
+    /// sampled with a mix of the old and new settings. Bytes still waiting in
+    /// the FIFOs are kept: bytes in the transmit FIFO are sent with the new
+    /// parity, and bytes in the receive FIFO were checked with the old one.
+    /// A frame that starts arriving while the baud clock is stopped is
+    /// received incorrectly, so the change should be made while the other
+    /// side is silent (e.g. after the command that negotiates it has been
+    /// acknowledged).
+    ///
+    /// Example:
+    /// ```
+    /// // Switch from 8N1 to 8E1 after negotiating it
+    /// uart.write_bytes(b"PARITY EVEN\r\n");
+    /// uart.set_parity(hal::uart::ParityBit::Even);
+    /// ```
+    pub fn set_parity(&mut self, parity: ParityBit) {
+        loop {
+            let status = self.uart.status().read();
+            if status.tx_busy().bit_is_clear()
+                && status.tx_em().bit_is_set()
+                && status.rx_busy().bit_is_clear()
+            {
+                break;
+            }
+        }
+        self.uart.ctrl().modify(|_, w| w.bclken().clear_bit());
+        self.uart.ctrl().modify(|_, w| {
+            w.par_eo().clear_bit().par_md().clear_bit();
+            match parity {
+                ParityBit::None => w.par_en().clear_bit(),
+                ParityBit::Even => w.par_en().set_bit().par_eo().clear_bit(),
+                ParityBit::Odd => w.par_en().set_bit().par_eo().set_bit(),
+                ParityBit::SpaceZero => w.par_en().set_bit().par_md().clear_bit(),
+                ParityBit::MarkOne => w.par_en().set_bit().par_md().set_bit(),
+            };
+            w
+        });
+        self.uart.ctrl().modify(|_, w| w.bclken().set_bit());
+        // Wait until baud clock is ready
+        while self.uart.ctrl().read().bclkrdy().bit_is_clear() {}
+    }
+
+    /// Returns the current status flags of the UART peripheral.
+    ///
+    /// The FIFO flags and levels are decoded from a single read of the status
+    /// register, so they are consistent with each other.
+    pub fn status(&self) -> UartStatus {
+        let status = self.uart.status().read();
+        UartStatus {
+            tx_busy: status.tx_busy().bit_is_set(),
+            rx_busy: status.rx_busy().bit_is_set(),
+            tx_empty: status.tx_em().bit_is_set(),
+            tx_full: status.tx_full().bit_is_set(),
+            rx_empty: status.rx_em().bit_is_set(),
+            rx_full: status.rx_full().bit_is_set(),
+            tx_level: status.tx_lvl().bits(),
+            rx_level: status.rx_lvl().bits(),
+            cts: self.uart.pnr().read().cts().bit_is_set(),
+        }
+    }
+
+    /// Returns the receive error flags without clearing them, and without
+    /// consuming any received byte. See [`UartErrors`] for when each flag is
+    /// set and cleared.
+    ///
+    /// Example:
+    /// ```
+    /// if uart.error_flags().any() {
+    ///     // Abort the transfer
+    ///     uart.clear_error_flags();
+    /// }
+    /// ```
+    pub fn error_flags(&self) -> UartErrors {
+        let int_fl = self.uart.int_fl().read();
+        UartErrors {
+            frame: int_fl.rx_ferr().bit_is_set(),
+            parity: int_fl.rx_par().bit_is_set(),
+            overrun: int_fl.rx_ov().bit_is_set(),
+        }
+    }
+
+    /// Clears the receive error flags reported by
+    /// [`BuiltUartPeripheral::error_flags`]. The receive FIFO is not
+    /// affected.
+    pub fn clear_error_flags(&self) {
+        // Interrupt flags are write 1 to clear
+        self.uart
+            .int_fl()
+            .write(|w| w.rx_ferr().set_bit().rx_par().set_bit().rx_ov().set_bit());
+    }
+
+    /// Returns a reference to the underlying UART peripheral from the PAC,
+    /// for access to registers that the HAL does not wrap yet.
+    ///
+    /// Note: Modifying registers through the PAC (which is possible through
+    /// a shared reference) may invalidate assumptions made by the HAL, such
+    /// as the configured clock, baud rate, or frame format.
+    pub fn inner(&self) -> &UART {
+        &self.uart
+    }
+
+    /// Returns a mutable reference to the underlying UART peripheral from the
+    /// PAC, for access to registers that the HAL does not wrap yet.
+    ///
+    /// # Safety
+    /// The caller must ensure that any changes made to the peripheral do not
+    /// invalidate assumptions made by the HAL (e.g. the clock source, baud
+    /// rate, frame format, or FIFO state), or that the HAL methods are not
+    /// used after such changes.
+    pub unsafe fn inner_mut(&mut self) -> &mut UART {
+        &mut self.uart
+    }
+}
+
+impl<UART, RX, TX, CTS, RTS> BuiltUartPeripheral<UART, RX, TX, CTS, RTS>
+where
+    UART: UartInterrupt,
+{
+    /// Returns the interrupt vector of the UART peripheral, e.g. for
+    /// unmasking it in the NVIC.
+    ///
+    /// Example:
+    /// ```
+    /// unsafe { cortex_m::peripheral::NVIC::unmask(uart.interrupt()) };
+    /// ```
+    pub fn interrupt(&self) -> crate::Interrupt {
+        UART::INTERRUPT
+    }
 }
 
 // Embedded HAL non-blocking serial traits
@@ -514,7 +1314,17 @@ where
     UART: Deref<Target = UartRegisterBlock>,
 {
     fn read(&mut self) -> nb::Result<u8, Self::Error> {
-        self._read_byte()
+        // Bytes buffered by BufRead are returned first
+        if let Some(byte) = self._pop_buffered() {
+            return Ok(byte);
+        }
+        if self._take_rx_overrun() {
+            return Err(nb::Error::Other(serial::ErrorKind::Overrun));
+        }
+        let byte = self._read_byte()?;
+        self._take_rx_error()
+            .map_err(|e| nb::Error::Other(serial::Error::kind(&e)))?;
+        Ok(byte)
     }
 }
 
@@ -527,8 +1337,7 @@ where
     }
 
     fn flush(&mut self) -> nb::Result<(), Self::Error> {
-        self.flush_tx();
-        Ok(())
+        self._flush_tx()
     }
 }
 
@@ -537,14 +1346,32 @@ impl<UART, RX, TX, CTS, RTS> embedded_io::ErrorType for BuiltUartPeripheral<UART
 where
     UART: Deref<Target = UartRegisterBlock>,
 {
-    type Error = core::convert::Infallible;
+    type Error = UartError;
 }
 
 impl<UART, RX, TX, CTS, RTS> embedded_io::Read for BuiltUartPeripheral<UART, RX, TX, CTS, RTS>
 where
     UART: Deref<Target = UartRegisterBlock>,
 {
+    /// Reads at least one byte into the buffer. This is a blocking operation.
+    ///
+    /// If the receive FIFO has overrun, [`UartError::Overrun`] is returned
+    /// and the overrun is cleared. The bytes still in the receive FIFO were
+    /// received before the dropped ones, so they are kept and returned by
+    /// the next read. If a framing or parity error is detected while
+    /// reading, the bytes read by this call are discarded and the error is
+    /// returned.
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        // Bytes buffered by BufRead are returned first
+        if self.rx_start < self.rx_end {
+            let count = buf.len().min(self.rx_end - self.rx_start);
+            buf[..count].copy_from_slice(&self.rx_buf[self.rx_start..self.rx_start + count]);
+            self.rx_start += count;
+            return Ok(count);
+        }
+        if self._take_rx_overrun() {
+            return Err(UartError::Overrun);
+        }
         let mut count = 0;
         if buf.len() == 0 {
             return Ok(0);
@@ -563,6 +1390,7 @@ where
                 count += 1;
             }
         }
+        self._take_rx_error()?;
         Ok(count)
     }
 }
@@ -572,7 +1400,38 @@ where
     UART: Deref<Target = UartRegisterBlock>,
 {
     fn read_ready(&mut self) -> Result<bool, Self::Error> {
-        Ok(!self._is_rx_empty())
+        Ok(self.rx_start < self.rx_end || !self._is_rx_empty())
+    }
+}
+
+impl<UART, RX, TX, CTS, RTS> embedded_io::BufRead for BuiltUartPeripheral<UART, RX, TX, CTS, RTS>
+where
+    UART: Deref<Target = UartRegisterBlock>,
+{
+    fn fill_buf(&mut self) -> Result<&[u8], Self::Error> {
+        if self.rx_start == self.rx_end {
+            if self._take_rx_overrun() {
+                return Err(UartError::Overrun);
+            }
+            // Block until at least one byte is available, then take all bytes
+            // currently in the receive FIFO
+            self.rx_buf[0] = self.read_byte();
+            self.rx_start = 0;
+            self.rx_end = 1;
+            while self.rx_end < BUF_READ_SIZE && !self._is_rx_empty() {
+                self.rx_buf[self.rx_end] = self.read_byte();
+                self.rx_end += 1;
+            }
+            if let Err(e) = self._take_rx_error() {
+                self.rx_end = 0;
+                return Err(e);
+            }
+        }
+        Ok(&self.rx_buf[self.rx_start..self.rx_end])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.rx_start = (self.rx_start + amt).min(self.rx_end);
     }
 }
 
@@ -601,3 +1460,547 @@ where
         Ok(!self._is_tx_full())
     }
 }
+
+/// Formatted output with [`core::write!`], e.g. for debug messages.
+///
+/// Each piece of the formatted output is queued with the blocking
+/// [`BuiltUartPeripheral::write_bytes`] in order, so output spanning several
+/// pieces is never reordered or truncated. Like `write_bytes`, this returns
+/// once the last bytes are in the transmit FIFO; use
+/// [`embedded_io::Write::flush`] to wait until they have been sent (e.g.
+/// before entering a low power mode).
+///
+/// Example:
+/// ```
+/// use core::fmt::Write;
+/// write!(uart, "x = {}\r\n", x).unwrap();
+/// ```
+impl<UART, RX, TX, CTS, RTS> core::fmt::Write for BuiltUartPeripheral<UART, RX, TX, CTS, RTS>
+where
+    UART: Deref<Target = UartRegisterBlock>,
+{
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// # Buffered UART Transmitter
+///
+/// Adapter over a [`BuiltUartPeripheral`] that queues written bytes in a
+/// `'static` ring buffer and drains it into the transmit FIFO from the TX
+/// half empty interrupt, so [`embedded_io::Write::write`] returns as soon as
+/// the bytes are queued instead of blocking for the whole transmission.
+///
+/// The UART interrupt must be unmasked in the NVIC (see
+/// [`BuiltUartPeripheral::interrupt`]) and its handler must call
+/// [`BufferedUartTx::on_tx_interrupt`]. The adapter is shared between the
+/// handler and the main code, e.g. in a `Mutex`.
+///
+/// Backpressure: if the buffer is full, `write` blocks until there is room
+/// for at least one byte, then returns a short write for the bytes that fit
+/// ([`embedded_io::Write::write_all`] keeps going until everything is
+/// queued). While blocked, `write` moves bytes from the buffer into the
+/// transmit FIFO itself, so it cannot deadlock when called inside a critical
+/// section that masks the UART interrupt. [`embedded_io::WriteReady`] reports
+/// whether a write would block.
+///
+/// Example:
+/// ```
+/// static mut TX_BUF: [u8; 256] = [0; 256];
+/// let tx_buf = unsafe { &mut *core::ptr::addr_of_mut!(TX_BUF) };
+/// let mut tx = hal::uart::BufferedUartTx::new(uart, tx_buf);
+/// tx.write_all(b"Hello, world!\r\n").unwrap();
+///
+/// #[interrupt]
+/// fn UART0() {
+///     // The adapter must be shared with the handler, e.g. in a Mutex
+///     tx.on_tx_interrupt();
+/// }
+/// ```
+pub struct BufferedUartTx<UART, RX, TX, CTS, RTS> {
+    uart: BuiltUartPeripheral<UART, RX, TX, CTS, RTS>,
+    buf: &'static mut [u8],
+    head: usize,
+    len: usize,
+}
+
+impl<UART, RX, TX, CTS, RTS> BufferedUartTx<UART, RX, TX, CTS, RTS>
+where
+    UART: Deref<Target = UartRegisterBlock>,
+{
+    /// Wrap a built UART peripheral, using `buf` to queue bytes for
+    /// transmission.
+    ///
+    /// ## Panics
+    /// Panics if `buf` is empty.
+    pub fn new(uart: BuiltUartPeripheral<UART, RX, TX, CTS, RTS>, buf: &'static mut [u8]) -> Self {
+        assert!(!buf.is_empty(), "UART transmit buffer must not be empty");
+        Self {
+            uart,
+            buf,
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Wait until all queued bytes have been sent, then release the UART
+    /// peripheral and the buffer.
+    pub fn free(
+        mut self,
+    ) -> (
+        BuiltUartPeripheral<UART, RX, TX, CTS, RTS>,
+        &'static mut [u8],
+    ) {
+        self._drain();
+        (self.uart, self.buf)
+    }
+
+    /// Move as many queued bytes as fit into the transmit FIFO.
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _pump(&mut self) {
+        // The queued bytes may wrap around the end of the buffer, so they are
+        // written in up to two contiguous parts
+        for _ in 0..2 {
+            let end = (self.head + self.len).min(self.buf.len());
+            let count = self.uart.fill_tx_fifo(&self.buf[self.head..end]);
+            self.head = (self.head + count) % self.buf.len();
+            self.len -= count;
+            if count == 0 || self.len == 0 {
+                break;
+            }
+        }
+    }
+
+    /// Wait until the buffer and the transmit FIFO are empty.
+    #[doc(hidden)]
+    #[inline(always)]
+    fn _drain(&mut self) {
+        while self.len > 0 {
+            self._pump();
+        }
+        self.uart.uart.int_en().modify(|_, w| w.tx_he().clear_bit());
+        self.uart.flush_tx();
+    }
+
+    /// Handle the TX half empty interrupt by refilling the transmit FIFO
+    /// from the buffer. The interrupt is disabled once the buffer is empty.
+    /// This must be called from the interrupt handler of the UART
+    /// peripheral.
+    pub fn on_tx_interrupt(&mut self) {
+        if self.uart.uart.int_fl().read().tx_he().bit_is_clear() {
+            return;
+        }
+        // Interrupt flags are write 1 to clear
+        self.uart.uart.int_fl().write(|w| w.tx_he().set_bit());
+        self._pump();
+        if self.len == 0 {
+            self.uart.uart.int_en().modify(|_, w| w.tx_he().clear_bit());
+        }
+    }
+
+    /// Returns the number of bytes waiting in the buffer. Bytes already in
+    /// the transmit FIFO are not included.
+    pub fn queued(&self) -> usize {
+        self.len
+    }
+}
+
+impl<UART, RX, TX, CTS, RTS> embedded_io::ErrorType for BufferedUartTx<UART, RX, TX, CTS, RTS>
+where
+    UART: Deref<Target = UartRegisterBlock>,
+{
+    type Error = core::convert::Infallible;
+}
+
+impl<UART, RX, TX, CTS, RTS> embedded_io::Write for BufferedUartTx<UART, RX, TX, CTS, RTS>
+where
+    UART: Deref<Target = UartRegisterBlock>,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let cap = self.buf.len();
+        while self.len == cap {
+            self._pump();
+        }
+        let count = (cap - self.len).min(buf.len());
+        for (i, byte) in buf[..count].iter().enumerate() {
+            self.buf[(self.head + self.len + i) % cap] = *byte;
+        }
+        self.len += count;
+        // Start transmitting right away, and leave the rest to the interrupt
+        self._pump();
+        if self.len > 0 {
+            // Interrupt flags are write 1 to clear
+            self.uart.uart.int_fl().write(|w| w.tx_he().set_bit());
+            self.uart.uart.int_en().modify(|_, w| w.tx_he().set_bit());
+        }
+        Ok(count)
+    }
+
+    /// Blocks until the buffer and the transmit FIFO are empty.
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self._drain();
+        Ok(())
+    }
+}
+
+impl<UART, RX, TX, CTS, RTS> embedded_io::WriteReady for BufferedUartTx<UART, RX, TX, CTS, RTS>
+where
+    UART: Deref<Target = UartRegisterBlock>,
+{
+    fn write_ready(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.len < self.buf.len())
+    }
+}
+
+impl<UART, RX, TX, CTS, RTS> BuiltUartPeripheral<UART, RX, TX, CTS, RTS>
+where
+    UART: Deref<Target = UartRegisterBlock>,
+{
+    /// Switch to interrupt-driven reception into a `'static` ring buffer.
+    /// See [`BufferedUartRx`].
+    ///
+    /// ## Panics
+    /// Panics if `buf` is empty.
+    pub fn into_interrupt_rx(
+        self,
+        buf: &'static mut [u8],
+    ) -> BufferedUartRx<UART, RX, TX, CTS, RTS> {
+        BufferedUartRx::new(self, buf)
+    }
+}
+
+/// # Buffered UART Receiver
+///
+/// Adapter over a [`BuiltUartPeripheral`] that drains the receive FIFO into
+/// a `'static` ring buffer from the RX FIFO threshold interrupt, so received
+/// bytes can be picked up with the non-blocking [`BufferedUartRx::try_read`]
+/// whenever the application gets to them.
+///
+/// The UART interrupt must be unmasked in the NVIC (see
+/// [`BuiltUartPeripheral::interrupt`]) and its handler must call
+/// [`BufferedUartRx::on_rx_interrupt`]. The adapter is shared between the
+/// handler and the main code, e.g. in a `Mutex`.
+///
+/// If the ring buffer is full, newly received bytes are dropped (the oldest
+/// bytes are kept) and an overrun is reported by
+/// [`BufferedUartRx::take_overrun`]. Overruns of the hardware receive FIFO,
+/// when the interrupt is serviced too late, are reported the same way.
+///
+/// Example:
+/// ```
+/// static mut RX_BUF: [u8; 256] = [0; 256];
+/// let rx_buf = unsafe { &mut *core::ptr::addr_of_mut!(RX_BUF) };
+/// let mut rx = uart.into_interrupt_rx(rx_buf);
+///
+/// #[interrupt]
+/// fn UART0() {
+///     // The adapter must be shared with the handler, e.g. in a Mutex
+///     rx.on_rx_interrupt();
+/// }
+///
+/// while let Some(byte) = rx.try_read() {
+///     // Handle the byte
+/// }
+/// ```
+pub struct BufferedUartRx<UART, RX, TX, CTS, RTS> {
+    uart: BuiltUartPeripheral<UART, RX, TX, CTS, RTS>,
+    buf: &'static mut [u8],
+    head: usize,
+    len: usize,
+    overrun: bool,
+}
+
+impl<UART, RX, TX, CTS, RTS> BufferedUartRx<UART, RX, TX, CTS, RTS>
+where
+    UART: Deref<Target = UartRegisterBlock>,
+{
+    /// Wrap a built UART peripheral, and enable the RX FIFO threshold
+    /// interrupt with a threshold of one byte.
+    ///
+    /// ## Panics
+    /// Panics if `buf` is empty.
+    pub fn new(uart: BuiltUartPeripheral<UART, RX, TX, CTS, RTS>, buf: &'static mut [u8]) -> Self {
+        assert!(!buf.is_empty(), "UART receive buffer must not be empty");
+        // Interrupt on every received byte, since the UART has no receive
+        // timeout to flush a partially filled FIFO
+        uart.uart
+            .ctrl()
+            .modify(|_, w| unsafe { w.rx_thd_val().bits(1) });
+        // Interrupt flags are write 1 to clear
+        uart.uart
+            .int_fl()
+            .write(|w| w.rx_thd().set_bit().rx_ov().set_bit());
+        uart.uart
+            .int_en()
+            .modify(|_, w| w.rx_thd().set_bit().rx_ov().set_bit());
+        Self {
+            uart,
+            buf,
+            head: 0,
+            len: 0,
+            overrun: false,
+        }
+    }
+
+    /// Disable the receive interrupts, and release the UART peripheral and
+    /// the buffer. Bytes left in the buffer are discarded.
+    pub fn free(
+        self,
+    ) -> (
+        BuiltUartPeripheral<UART, RX, TX, CTS, RTS>,
+        &'static mut [u8],
+    ) {
+        self.uart
+            .uart
+            .int_en()
+            .modify(|_, w| w.rx_thd().clear_bit().rx_ov().clear_bit());
+        (self.uart, self.buf)
+    }
+
+    /// Handle the RX FIFO threshold interrupt by moving all bytes from the
+    /// receive FIFO into the ring buffer. This must be called from the
+    /// interrupt handler of the UART peripheral.
+    pub fn on_rx_interrupt(&mut self) {
+        // The bytes still in the receive FIFO are valid, so only the flags are
+        // cleared, and the FIFO is drained below
+        let int_fl = self.uart.uart.int_fl().read();
+        if int_fl.rx_ov().bit_is_set() {
+            self.overrun = true;
+        }
+        // Interrupt flags are write 1 to clear
+        self.uart
+            .uart
+            .int_fl()
+            .write(|w| w.rx_thd().set_bit().rx_ov().set_bit());
+        let cap = self.buf.len();
+        while let Ok(byte) = self.uart._read_byte() {
+            if self.len == cap {
+                self.overrun = true;
+                continue;
+            }
+            self.buf[(self.head + self.len) % cap] = byte;
+            self.len += 1;
+        }
+    }
+
+    /// Returns the oldest byte from the ring buffer, or [`None`] if it is
+    /// empty. This is a non-blocking operation.
+    pub fn try_read(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.buf[self.head];
+        self.head = (self.head + 1) % self.buf.len();
+        self.len -= 1;
+        Some(byte)
+    }
+
+    /// Returns the number of bytes waiting in the ring buffer.
+    pub fn available(&self) -> usize {
+        self.len
+    }
+
+    /// Returns [`true`] if received bytes were dropped since the last call,
+    /// because the ring buffer or the receive FIFO was full, and clears the
+    /// flag.
+    pub fn take_overrun(&mut self) -> bool {
+        core::mem::replace(&mut self.overrun, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::UnsafeCell;
+
+    /// UART register block backed by memory, for testing register logic on
+    /// the host. Reads return the last written value, so write 1 to clear
+    /// flags and the FIFO have to be updated by the test.
+    #[repr(C, align(4))]
+    struct FakeUart(UnsafeCell<[u8; core::mem::size_of::<UartRegisterBlock>()]>);
+
+    impl Deref for FakeUart {
+        type Target = UartRegisterBlock;
+
+        fn deref(&self) -> &UartRegisterBlock {
+            // Safety: The register block only holds volatile cells of plain
+            // integers, for which any bit pattern is valid
+            unsafe { &*(self.0.get() as *const UartRegisterBlock) }
+        }
+    }
+
+    impl FakeUart {
+        /// Sets the read-only status register.
+        fn set_status(&self, bits: u32) {
+            // Safety: The register is backed by memory owned by the fake
+            unsafe { self.status().as_ptr().write_volatile(bits) };
+        }
+    }
+
+    fn fake_uart() -> BuiltUartPeripheral<FakeUart, (), (), (), ()> {
+        BuiltUartPeripheral {
+            uart: FakeUart(UnsafeCell::new(
+                [0; core::mem::size_of::<UartRegisterBlock>()],
+            )),
+            _rx_pin: (),
+            _tx_pin: (),
+            _cts_pin: (),
+            _rts_pin: (),
+            clk_src_freq: 7_372_800,
+            tx_pending: &[],
+            rx_buf: [0; BUF_READ_SIZE],
+            rx_start: 0,
+            rx_end: 0,
+        }
+    }
+
+    #[test]
+    fn read_until_exact_fit() {
+        let mut uart = fake_uart();
+        // A message as long as the buffer (here empty), then the delimiter
+        uart.uart.fifo().write(|w| unsafe { w.data().bits(b';') });
+        assert_eq!(uart.read_until(b';', &mut []), Ok(0));
+        // A message longer than the buffer
+        uart.uart.fifo().write(|w| unsafe { w.data().bits(b'x') });
+        let mut buf = [0; 3];
+        assert_eq!(uart.read_until(b';', &mut buf), Err(OverflowError));
+        assert_eq!(&buf, b"xxx");
+    }
+
+    #[test]
+    fn read_until_bufread_first() {
+        let mut uart = fake_uart();
+        // Bytes left in the BufRead buffer come before the FIFO
+        uart.rx_buf[..3].copy_from_slice(b"ab;");
+        uart.rx_end = 3;
+        uart.uart.fifo().write(|w| unsafe { w.data().bits(b'x') });
+        let mut buf = [0; 4];
+        assert_eq!(uart.read_until(b';', &mut buf), Ok(2));
+        assert_eq!(&buf[..2], b"ab");
+    }
+
+    #[test]
+    fn rx_overrun_keeps_fifo() {
+        use embedded_io::Read;
+        let mut uart = fake_uart();
+        // The receive FIFO holds valid bytes received before the overrun
+        uart.uart.fifo().write(|w| unsafe { w.data().bits(0x42) });
+        // The overrun is pending along with another flag
+        uart.uart
+            .int_fl()
+            .write(|w| w.rx_ov().set_bit().tx_he().set_bit());
+        let mut buf = [0; 4];
+        assert_eq!(uart.read(&mut buf), Err(UartError::Overrun));
+        // The fake keeps the last value written, so the write 1 to clear of
+        // only rx_ov shows up as rx_ov set and tx_he clear. The receive FIFO
+        // is not flushed.
+        let int_fl = uart.uart.int_fl().read();
+        assert!(int_fl.rx_ov().bit_is_set(), "W1C write of rx_ov observed");
+        assert!(int_fl.tx_he().bit_is_clear());
+        assert!(uart.uart.ctrl().read().rx_flush().bit_is_clear());
+        uart.uart.int_fl().write(|w| unsafe { w.bits(0) });
+        // The stream continues with the bytes still in the FIFO
+        assert_eq!(uart.read(&mut buf), Ok(4));
+        assert_eq!(buf, [0x42; 4]);
+    }
+
+    #[test]
+    fn flush_waits_for_tx_empty() {
+        use serial::Write;
+        let mut uart = fake_uart();
+        assert_eq!(uart.flush(), Err(nb::Error::WouldBlock));
+        // Status bit 6 is TX_EM
+        uart.uart.set_status(1 << 6);
+        assert_eq!(uart.flush(), Ok(()));
+    }
+
+    #[test]
+    fn set_parity_bits() {
+        let mut uart = fake_uart();
+        // The transmitter and receiver are idle, and the baud clock is ready
+        uart.uart.set_status(1 << 6);
+        uart.uart.ctrl().write(|w| w.bclkrdy().set_bit());
+        // (parity, par_en, par_eo, par_md)
+        let cases = [
+            (ParityBit::None, false, false, false),
+            (ParityBit::Even, true, false, false),
+            (ParityBit::Odd, true, true, false),
+            (ParityBit::MarkOne, true, false, true),
+        ];
+        for (parity, par_en, par_eo, par_md) in cases {
+            uart.set_parity(parity);
+            let ctrl = uart.uart.ctrl().read();
+            assert_eq!(ctrl.par_en().bit_is_set(), par_en);
+            assert_eq!(ctrl.par_eo().bit_is_set(), par_eo);
+            assert_eq!(ctrl.par_md().bit_is_set(), par_md);
+            assert!(ctrl.bclken().bit_is_set());
+            assert_eq!(uart.config().parity, parity);
+        }
+    }
+
+    #[test]
+    fn clkdiv_below_one_is_too_fast() {
+        assert_eq!(compute_clkdiv(1000, 0), Err(BaudError::TooFast));
+        assert_eq!(compute_clkdiv(1000, 1001), Err(BaudError::TooFast));
+        assert_eq!(compute_clkdiv(32_768, 115_200), Err(BaudError::TooFast));
+    }
+
+    #[test]
+    fn clkdiv_of_one() {
+        assert_eq!(compute_clkdiv(115_200, 115_200), Ok((1, 115_200)));
+        // Rounds up to a divisor of 1 just below the clock source frequency
+        assert_eq!(compute_clkdiv(1000, 990), Ok((1, 1000)));
+    }
+
+    #[test]
+    fn clkdiv_ertco() {
+        assert_eq!(compute_clkdiv(32_768, 300), Ok((109, 300)));
+        assert_eq!(compute_clkdiv(32_768, 1200), Ok((27, 1213)));
+        assert_eq!(compute_clkdiv(32_768, 2400), Err(BaudError::Inaccurate));
+        assert_eq!(compute_clkdiv(32_768, 9600), Err(BaudError::Inaccurate));
+    }
+
+    #[test]
+    fn clkdiv_ibro_standard_rates() {
+        let rates = [
+            (9600, 768),
+            (19_200, 384),
+            (38_400, 192),
+            (57_600, 128),
+            (115_200, 64),
+            (230_400, 32),
+            (460_800, 16),
+            (921_600, 8),
+        ];
+        for (baud, clkdiv) in rates {
+            assert_eq!(compute_clkdiv(7_372_800, baud), Ok((clkdiv, baud)));
+        }
+    }
+
+    #[test]
+    fn clkdiv_max() {
+        assert_eq!(compute_clkdiv(MAX_CLKDIV, 1), Ok((MAX_CLKDIV, 1)));
+        assert_eq!(compute_clkdiv(MAX_CLKDIV + 1, 1), Err(BaudError::TooSlow));
+        assert_eq!(compute_clkdiv(100_000_000, 95), Err(BaudError::TooSlow));
+    }
+
+    #[test]
+    fn clkdiv_tolerance_boundary() {
+        // 1020 baud is exactly BAUD_TOLERANCE_PERMILLE (2%) above 1000 baud
+        assert_eq!(BAUD_TOLERANCE_PERMILLE, 20);
+        assert_eq!(compute_clkdiv(1020, 1000), Ok((1, 1020)));
+        // 980 baud is exactly 2% below
+        assert_eq!(compute_clkdiv(1960, 1000), Ok((2, 980)));
+        assert_eq!(compute_clkdiv(1021, 1000), Err(BaudError::Inaccurate));
+        assert_eq!(compute_clkdiv_with_tolerance(1021, 1000, 21), Ok((1, 1021)));
+        assert_eq!(
+            compute_clkdiv_with_tolerance(1020, 1000, 19),
+            Err(BaudError::Inaccurate)
+        );
+    }
+}